@@ -4,7 +4,7 @@ use std::{borrow::Cow, process::Command};
 /// Generate the `cargo` key output based on the git commit sha
 pub fn generate_cargo_keys() {
     let output_result = Command::new("git")
-        .args(&["rev-parse", "--short", "HEAD"])
+        .args(["rev-parse", "--short", "HEAD"])
         .output();
 
     let commit = match output_result {
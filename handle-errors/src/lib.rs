@@ -1,30 +1,67 @@
 use argon2::Error as Argon2Error;
 use reqwest::Error as ReqwestError;
 use reqwest_middleware::Error as ReqwestMiddlewareError;
+use serde::Serialize;
 use std::fmt::{Display, Formatter};
 use tracing::{event, Level};
 use warp::reject::Reject;
 use warp::{
-    cors::CorsForbidden, filters::body::BodyDeserializeError, http::StatusCode, reply, Rejection,
-    Reply,
+    cors::CorsForbidden,
+    filters::body::BodyDeserializeError,
+    http::{header, StatusCode},
+    reply, Rejection, Reply,
 };
 
+/// Realm of the `WWW-Authenticate` header sent with the authentication errors
+const AUTH_REALM: &str = "questions_answers";
+
 #[derive(Debug)]
 pub enum Error {
     ParseError(std::num::ParseIntError),
     BadQuestionId,
     MissingParameters,
+    InvalidCursor,
+    CursorNotSupported,
+    InvalidSortParameter(String),
+    InvalidTag(String),
+    InvalidMatchParameter(String),
+    TagNotFound,
+    AccountNotFound,
+    AccountSuspended,
+    AccountBanned,
+    InvalidSuspension,
+    InvalidReassignment,
+    InvalidFlagStatus(String),
+    FlagNotFound,
+    InvalidSearchQuery,
     QuestionNotFound,
+    AnswerNotFound,
     DatabaseQueryError(sqlx::Error),
     ClientError(ApiLayerError),
     ServerError(ApiLayerError),
     ReqwestAPIError(ReqwestError),
     MiddlewareReqwestAPIError(ReqwestMiddlewareError),
     WrongPassword,
+    TooManyLoginAttempts(i64),
+    WeakPassword,
+    EmailNotVerified,
+    InvalidVerificationToken,
+    InvalidResetToken,
+    MailError(String),
+    EmailRejected(String),
     ArgonLibraryError(Argon2Error),
     TokenError,
+    MissingToken,
+    MalformedToken,
+    ExpiredToken,
+    TokenNotYetValid,
     Unauthorized,
     EnvVariableError,
+    AlreadyVoted,
+    CannotVoteOwnPost,
+    AlreadyFlagged,
+    ModerationUnavailable,
+    ContentRejected(Vec<BadWordSpan>),
 }
 
 #[derive(Debug, Clone)]
@@ -33,6 +70,22 @@ pub struct ApiLayerError {
     pub message: String,
 }
 
+/// Bad word found in a field of a rejected post. `start` and `end` are character positions.
+#[derive(Debug, Clone, Serialize)]
+pub struct BadWordSpan {
+    pub field: String,
+    pub original: String,
+    pub word: String,
+    pub start: i64,
+    pub end: i64,
+}
+
+#[derive(Debug, Serialize)]
+struct ContentRejectedBody<'a> {
+    error: &'static str,
+    bad_words: &'a [BadWordSpan],
+}
+
 impl Display for Error {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match &*self {
@@ -40,7 +93,29 @@ impl Display for Error {
             Error::MissingParameters => {
                 write!(f, "Missing parameters")
             }
+            Error::InvalidCursor => write!(f, "Invalid cursor"),
+            Error::CursorNotSupported => {
+                write!(f, "Cursor pagination is not supported by this sort order")
+            }
+            Error::InvalidSortParameter(sort) => write!(f, "Invalid sort parameter: {}", sort),
+            Error::InvalidTag(tag) => write!(f, "Invalid tag: {:?}", tag),
+            Error::InvalidMatchParameter(value) => write!(f, "Invalid match parameter: {}", value),
+            Error::TagNotFound => write!(f, "Tag not found"),
+            Error::AccountNotFound => write!(f, "Account not found"),
+            Error::AccountSuspended => write!(f, "The account is suspended."),
+            Error::AccountBanned => write!(f, "The account is banned."),
+            Error::InvalidSuspension => write!(f, "A suspension has to end in the future."),
+            Error::InvalidReassignment => {
+                write!(
+                    f,
+                    "Posts can only be reassigned to another existing account."
+                )
+            }
+            Error::InvalidFlagStatus(value) => write!(f, "Invalid flag status: {}", value),
+            Error::FlagNotFound => write!(f, "Flag not found"),
+            Error::InvalidSearchQuery => write!(f, "Invalid search query"),
             Error::QuestionNotFound => write!(f, "Question not found"),
+            Error::AnswerNotFound => write!(f, "Answer not found"),
             Error::BadQuestionId => write!(f, "Question id must be an integer"),
             Error::DatabaseQueryError(err) => write!(f, "Database error: {}", err),
             Error::ReqwestAPIError(err) => write!(f, "External API error: {}", err),
@@ -48,10 +123,36 @@ impl Display for Error {
             Error::ClientError(err) => write!(f, "External client error: {}", err),
             Error::ServerError(err) => write!(f, "External server error: {}", err),
             Error::WrongPassword => write!(f, "Incorrect credentials"),
+            Error::TooManyLoginAttempts(retry_after) => write!(
+                f,
+                "Too many failed logins, try again in {} seconds.",
+                retry_after
+            ),
+            Error::EmailNotVerified => write!(f, "The email of the account is not verified."),
+            Error::InvalidVerificationToken => {
+                write!(f, "The email verification link is not valid.")
+            }
+            Error::WeakPassword => write!(f, "The password does not follow the password policy."),
+            Error::MailError(err) => write!(f, "Cannot send the email: {}", err),
+            Error::EmailRejected(err) => write!(f, "The email cannot be sent: {}", err),
+            Error::InvalidResetToken => write!(f, "The password reset token is not valid."),
             Error::ArgonLibraryError(err) => write!(f, "Cannot verify password: {}", err),
             Error::TokenError => write!(f, "Token error."),
+            Error::MissingToken => write!(f, "The Authorization header is missing."),
+            Error::MalformedToken => {
+                write!(f, "The Authorization header is not a Bearer token.")
+            }
+            Error::ExpiredToken => write!(f, "The token has expired."),
+            Error::TokenNotYetValid => write!(f, "The token is not valid yet."),
             Error::Unauthorized => write!(f, "Unauthorized."),
             Error::EnvVariableError => write!(f, "Environment variable is missing."),
+            Error::AlreadyVoted => write!(f, "The account has already voted this post."),
+            Error::CannotVoteOwnPost => write!(f, "An account cannot vote its own posts."),
+            Error::AlreadyFlagged => write!(f, "The account has already flagged this post."),
+            Error::ModerationUnavailable => write!(f, "The moderation service is unavailable."),
+            Error::ContentRejected(spans) => {
+                write!(f, "The content contains {} bad words.", spans.len())
+            }
         }
     }
 }
@@ -66,7 +167,95 @@ impl Display for ApiLayerError {
 
 impl Reject for ApiLayerError {}
 
-pub async fn error_handler(rej: Rejection) -> Result<impl Reply, std::convert::Infallible> {
+pub async fn error_handler(rej: Rejection) -> Result<reply::Response, std::convert::Infallible> {
+    // Rejected posts are the only errors with a JSON body, which lists the offending words
+    if let Some(Error::ContentRejected(spans)) = rej.find() {
+        event!(Level::ERROR, "Content rejected: {} bad words.", spans.len());
+
+        return Ok(reply::with_status(
+            reply::json(&ContentRejectedBody {
+                error: "CONTENT_REJECTED",
+                bad_words: spans,
+            }),
+            StatusCode::UNPROCESSABLE_ENTITY,
+        )
+        .into_response());
+    }
+
+    if let Some(Error::TooManyLoginAttempts(retry_after)) = rej.find() {
+        event!(Level::ERROR, "Too many failed logins.");
+
+        return Ok(reply::with_header(
+            reply::with_status("TOO_MANY_LOGIN_ATTEMPTS", StatusCode::TOO_MANY_REQUESTS),
+            header::RETRY_AFTER,
+            retry_after.to_string(),
+        )
+        .into_response());
+    }
+
+    if let Some(err) = rej.find::<Error>() {
+        if let Some(response) = bearer_error(err) {
+            event!(Level::ERROR, "{}", err);
+
+            return Ok(response);
+        }
+    }
+
+    status_handler(rej).await.map(Reply::into_response)
+}
+
+/// Responses of the authentication errors, with the `WWW-Authenticate` header of RFC 6750.
+fn bearer_error(err: &Error) -> Option<reply::Response> {
+    let (code, status, challenge) = match err {
+        Error::MissingToken => (
+            "MISSING_TOKEN",
+            StatusCode::UNAUTHORIZED,
+            format!("Bearer realm=\"{}\"", AUTH_REALM),
+        ),
+        Error::MalformedToken => (
+            "MALFORMED_TOKEN",
+            StatusCode::BAD_REQUEST,
+            bearer_challenge(
+                "invalid_request",
+                "The Authorization header is not a Bearer token",
+            ),
+        ),
+        Error::ExpiredToken => (
+            "TOKEN_EXPIRED",
+            StatusCode::UNAUTHORIZED,
+            bearer_challenge("invalid_token", "The access token expired"),
+        ),
+        Error::TokenNotYetValid => (
+            "TOKEN_NOT_YET_VALID",
+            StatusCode::UNAUTHORIZED,
+            bearer_challenge("invalid_token", "The access token is not valid yet"),
+        ),
+        Error::TokenError => (
+            "INVALID_TOKEN",
+            StatusCode::UNAUTHORIZED,
+            bearer_challenge("invalid_token", "The access token is invalid"),
+        ),
+        _ => return None,
+    };
+
+    Some(
+        reply::with_header(
+            reply::with_status(code, status),
+            header::WWW_AUTHENTICATE,
+            challenge,
+        )
+        .into_response(),
+    )
+}
+
+fn bearer_challenge(error: &str, description: &str) -> String {
+    format!(
+        "Bearer realm=\"{}\", error=\"{}\", error_description=\"{}\"",
+        AUTH_REALM, error, description
+    )
+}
+
+async fn status_handler(rej: Rejection) -> Result<impl Reply, std::convert::Infallible> {
     if rej.is_not_found() {
         Ok(reply::with_status("NOT_FOUND", StatusCode::NOT_FOUND))
     } else if let Some(err) = rej.find::<CorsForbidden>() {
@@ -147,17 +336,177 @@ pub async fn error_handler(rej: Rejection) -> Result<impl Reply, std::convert::I
             "INVALID_CREDENTIALS",
             StatusCode::UNAUTHORIZED,
         ))
-    } else if let Some(Error::TokenError) = rej.find() {
-        event!(Level::ERROR, "Token Error.");
+    } else if let Some(Error::InvalidCursor) = rej.find() {
+        event!(Level::ERROR, "Invalid cursor.");
 
         Ok(reply::with_status(
-            "INVALID_TOKEN",
-            StatusCode::UNAUTHORIZED,
+            "INVALID_CURSOR",
+            StatusCode::BAD_REQUEST,
+        ))
+    } else if let Some(Error::CursorNotSupported) = rej.find() {
+        event!(Level::ERROR, "Cursor not supported.");
+
+        Ok(reply::with_status(
+            "CURSOR_NOT_SUPPORTED: cursor pagination is only available on /questions with the newest and oldest sort orders",
+            StatusCode::BAD_REQUEST,
+        ))
+    } else if let Some(Error::InvalidSortParameter(sort)) = rej.find() {
+        event!(Level::ERROR, "Invalid sort parameter: {}", sort);
+
+        Ok(reply::with_status(
+            "INVALID_SORT_PARAMETER: expected one of newest, oldest, score, most_answers, unanswered, recently_active",
+            StatusCode::BAD_REQUEST,
+        ))
+    } else if let Some(Error::InvalidTag(tag)) = rej.find() {
+        event!(Level::ERROR, "Invalid tag: {:?}", tag);
+
+        Ok(reply::with_status(
+            "INVALID_TAG: tags can only contain letters, digits, '-', '+', '#' and '.', with at most 35 characters",
+            StatusCode::BAD_REQUEST,
+        ))
+    } else if let Some(Error::InvalidMatchParameter(value)) = rej.find() {
+        event!(Level::ERROR, "Invalid match parameter: {}", value);
+
+        Ok(reply::with_status(
+            "INVALID_MATCH_PARAMETER: expected one of all, any",
+            StatusCode::BAD_REQUEST,
+        ))
+    } else if let Some(Error::InvalidSearchQuery) = rej.find() {
+        event!(Level::ERROR, "Invalid search query.");
+
+        Ok(reply::with_status(
+            "INVALID_SEARCH_QUERY: the q parameter is required and it can have at most 200 characters",
+            StatusCode::BAD_REQUEST,
+        ))
+    } else if let Some(Error::InvalidFlagStatus(value)) = rej.find() {
+        event!(Level::ERROR, "Invalid flag status: {}", value);
+
+        Ok(reply::with_status(
+            "INVALID_STATUS_PARAMETER: expected one of open, resolved, dismissed",
+            StatusCode::BAD_REQUEST,
+        ))
+    } else if let Some(Error::FlagNotFound) = rej.find() {
+        event!(Level::ERROR, "Flag not found.");
+
+        Ok(reply::with_status("FLAG_NOT_FOUND", StatusCode::NOT_FOUND))
+    } else if let Some(Error::AccountNotFound) = rej.find() {
+        event!(Level::ERROR, "Account not found.");
+
+        Ok(reply::with_status(
+            "ACCOUNT_NOT_FOUND",
+            StatusCode::NOT_FOUND,
+        ))
+    } else if let Some(Error::AccountSuspended) = rej.find() {
+        event!(Level::ERROR, "Account suspended.");
+
+        Ok(reply::with_status(
+            "ACCOUNT_SUSPENDED",
+            StatusCode::FORBIDDEN,
+        ))
+    } else if let Some(Error::AccountBanned) = rej.find() {
+        event!(Level::ERROR, "Account banned.");
+
+        Ok(reply::with_status("ACCOUNT_BANNED", StatusCode::FORBIDDEN))
+    } else if let Some(Error::InvalidSuspension) = rej.find() {
+        event!(Level::ERROR, "Invalid suspension.");
+
+        Ok(reply::with_status(
+            "INVALID_SUSPENSION: the until date has to be in the future",
+            StatusCode::BAD_REQUEST,
+        ))
+    } else if let Some(Error::InvalidReassignment) = rej.find() {
+        event!(Level::ERROR, "Invalid reassignment.");
+
+        Ok(reply::with_status(
+            "INVALID_REASSIGNMENT: reassign_to has to be another existing account",
+            StatusCode::BAD_REQUEST,
+        ))
+    } else if let Some(Error::EmailNotVerified) = rej.find() {
+        event!(Level::ERROR, "Email not verified.");
+
+        Ok(reply::with_status(
+            "EMAIL_NOT_VERIFIED",
+            StatusCode::FORBIDDEN,
+        ))
+    } else if let Some(Error::InvalidVerificationToken) = rej.find() {
+        event!(Level::ERROR, "Invalid email verification token.");
+
+        Ok(reply::with_status(
+            "INVALID_VERIFICATION_TOKEN",
+            StatusCode::BAD_REQUEST,
+        ))
+    } else if let Some(Error::WeakPassword) = rej.find() {
+        event!(Level::ERROR, "Weak password.");
+
+        Ok(reply::with_status(
+            "WEAK_PASSWORD: passwords need 10 to 128 characters, a letter and a digit or symbol, and they cannot contain the email",
+            StatusCode::BAD_REQUEST,
+        ))
+    } else if let Some(Error::InvalidResetToken) = rej.find() {
+        event!(Level::ERROR, "Invalid password reset token.");
+
+        Ok(reply::with_status(
+            "INVALID_RESET_TOKEN",
+            StatusCode::BAD_REQUEST,
+        ))
+    } else if let Some(Error::MailError(err) | Error::EmailRejected(err)) = rej.find() {
+        event!(Level::ERROR, "{}", err);
+
+        Ok(reply::with_status(
+            "INTERNAL_SERVER_ERROR",
+            StatusCode::INTERNAL_SERVER_ERROR,
+        ))
+    } else if let Some(Error::MissingParameters) = rej.find() {
+        event!(Level::ERROR, "Missing parameters.");
+
+        Ok(reply::with_status(
+            "MISSING_PARAMETERS",
+            StatusCode::BAD_REQUEST,
+        ))
+    } else if let Some(Error::TagNotFound) = rej.find() {
+        event!(Level::ERROR, "Tag not found.");
+
+        Ok(reply::with_status("TAG_NOT_FOUND", StatusCode::NOT_FOUND))
+    } else if let Some(Error::QuestionNotFound) = rej.find() {
+        event!(Level::ERROR, "Question not found.");
+
+        Ok(reply::with_status(
+            "QUESTION_NOT_FOUND",
+            StatusCode::NOT_FOUND,
+        ))
+    } else if let Some(Error::AnswerNotFound) = rej.find() {
+        event!(Level::ERROR, "Answer not found.");
+
+        Ok(reply::with_status(
+            "ANSWER_NOT_FOUND",
+            StatusCode::NOT_FOUND,
         ))
     } else if let Some(Error::Unauthorized) = rej.find() {
         event!(Level::ERROR, "Unauthorized.");
 
         Ok(reply::with_status("FORBIDDEN", StatusCode::FORBIDDEN))
+    } else if let Some(Error::AlreadyVoted) = rej.find() {
+        event!(Level::ERROR, "Already voted.");
+
+        Ok(reply::with_status("ALREADY_VOTED", StatusCode::CONFLICT))
+    } else if let Some(Error::CannotVoteOwnPost) = rej.find() {
+        event!(Level::ERROR, "Cannot vote own post.");
+
+        Ok(reply::with_status(
+            "CANNOT_VOTE_OWN_POST",
+            StatusCode::FORBIDDEN,
+        ))
+    } else if let Some(Error::AlreadyFlagged) = rej.find() {
+        event!(Level::ERROR, "Already flagged.");
+
+        Ok(reply::with_status("ALREADY_FLAGGED", StatusCode::CONFLICT))
+    } else if let Some(Error::ModerationUnavailable) = rej.find() {
+        event!(Level::ERROR, "Moderation unavailable.");
+
+        Ok(reply::with_status(
+            "MODERATION_UNAVAILABLE",
+            StatusCode::SERVICE_UNAVAILABLE,
+        ))
     } else {
         event!(Level::ERROR, "Unknown error");
 
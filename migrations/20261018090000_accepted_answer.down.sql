@@ -0,0 +1,3 @@
+-- Add down migration script here
+ALTER TABLE questions
+DROP COLUMN accepted_answer;
\ No newline at end of file
@@ -0,0 +1,3 @@
+-- Add up migration script here
+ALTER TABLE questions
+ADD COLUMN accepted_answer integer REFERENCES answers ON DELETE SET NULL;
\ No newline at end of file
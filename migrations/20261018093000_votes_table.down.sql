@@ -0,0 +1,8 @@
+-- Add down migration script here
+ALTER TABLE answers
+DROP COLUMN score;
+
+ALTER TABLE questions
+DROP COLUMN score;
+
+DROP TABLE IF EXISTS votes;
\ No newline at end of file
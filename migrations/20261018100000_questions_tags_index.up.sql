@@ -0,0 +1,2 @@
+-- Add up migration script here
+CREATE INDEX IF NOT EXISTS questions_tags_idx ON questions USING GIN (tags);
\ No newline at end of file
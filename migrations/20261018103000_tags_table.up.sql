@@ -0,0 +1,18 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS tags (
+    id serial PRIMARY KEY,
+    name VARCHAR (35) NOT NULL UNIQUE,
+    description TEXT,
+    created_on TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+UPDATE questions
+SET tags = ARRAY(
+    SELECT DISTINCT lower(btrim(tag)) FROM unnest(tags) AS tag WHERE btrim(tag) <> ''
+)
+WHERE tags IS NOT NULL;
+
+INSERT INTO tags (name)
+SELECT DISTINCT tag FROM questions, unnest(questions.tags) AS tag
+WHERE char_length(tag) <= 35
+ON CONFLICT (name) DO NOTHING;
\ No newline at end of file
@@ -0,0 +1,2 @@
+-- Add up migration script here
+CREATE INDEX IF NOT EXISTS questions_created_on_id_idx ON questions (created_on, id);
\ No newline at end of file
@@ -0,0 +1,6 @@
+-- Add down migration script here
+ALTER TABLE answers
+DROP COLUMN moderation_status;
+
+ALTER TABLE questions
+DROP COLUMN moderation_status;
\ No newline at end of file
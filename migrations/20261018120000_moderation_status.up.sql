@@ -0,0 +1,10 @@
+-- Add up migration script here
+ALTER TABLE questions
+ADD COLUMN moderation_status VARCHAR (20) NOT NULL DEFAULT 'approved';
+
+ALTER TABLE answers
+ADD COLUMN moderation_status VARCHAR (20) NOT NULL DEFAULT 'approved';
+
+CREATE INDEX IF NOT EXISTS questions_moderation_status_idx ON questions (moderation_status) WHERE moderation_status <> 'approved';
+
+CREATE INDEX IF NOT EXISTS answers_moderation_status_idx ON answers (moderation_status) WHERE moderation_status <> 'approved';
\ No newline at end of file
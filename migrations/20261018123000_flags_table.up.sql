@@ -0,0 +1,25 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS flags (
+    id serial PRIMARY KEY,
+    account_id integer,
+    question_id integer REFERENCES questions ON DELETE CASCADE,
+    answer_id integer REFERENCES answers ON DELETE CASCADE,
+    reason VARCHAR (20) NOT NULL,
+    details TEXT,
+    status VARCHAR (20) NOT NULL DEFAULT 'open',
+    reviewed_by integer,
+    reviewed_on TIMESTAMP,
+    created_on TIMESTAMP NOT NULL DEFAULT NOW(),
+    CHECK ((question_id IS NULL) <> (answer_id IS NULL)),
+    UNIQUE (account_id, question_id),
+    UNIQUE (account_id, answer_id)
+);
+
+CREATE INDEX IF NOT EXISTS flags_status_idx ON flags (status, created_on);
+
+-- The flags raised by the moderation have no account, an open one per post is enough
+CREATE UNIQUE INDEX IF NOT EXISTS flags_system_question_idx ON flags (question_id)
+WHERE account_id IS NULL AND status = 'open';
+
+CREATE UNIQUE INDEX IF NOT EXISTS flags_system_answer_idx ON flags (answer_id)
+WHERE account_id IS NULL AND status = 'open';
\ No newline at end of file
@@ -0,0 +1,7 @@
+-- Add down migration script here
+ALTER TABLE accounts
+DROP COLUMN banned_on,
+DROP COLUMN suspended_until;
+
+ALTER TABLE accounts
+DROP CONSTRAINT accounts_id_key;
\ No newline at end of file
@@ -0,0 +1,7 @@
+-- Add up migration script here
+ALTER TABLE accounts
+ADD CONSTRAINT accounts_id_key UNIQUE (id);
+
+ALTER TABLE accounts
+ADD COLUMN suspended_until TIMESTAMPTZ,
+ADD COLUMN banned_on TIMESTAMPTZ;
\ No newline at end of file
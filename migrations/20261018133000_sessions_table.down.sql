@@ -0,0 +1,4 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS refresh_tokens;
+
+DROP TABLE IF EXISTS sessions;
\ No newline at end of file
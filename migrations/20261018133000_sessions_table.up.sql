@@ -0,0 +1,18 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS sessions (
+    id UUID PRIMARY KEY,
+    account_id integer NOT NULL REFERENCES accounts (id) ON DELETE CASCADE,
+    created_on TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    revoked_on TIMESTAMPTZ
+);
+
+CREATE TABLE IF NOT EXISTS refresh_tokens (
+    id serial PRIMARY KEY,
+    session_id UUID NOT NULL REFERENCES sessions ON DELETE CASCADE,
+    token_hash VARCHAR (64) NOT NULL UNIQUE,
+    expires_on TIMESTAMPTZ NOT NULL,
+    used_on TIMESTAMPTZ,
+    created_on TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS sessions_account_id_idx ON sessions (account_id);
\ No newline at end of file
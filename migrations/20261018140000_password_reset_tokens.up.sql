@@ -0,0 +1,11 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS password_reset_tokens (
+    id serial PRIMARY KEY,
+    account_id integer NOT NULL REFERENCES accounts (id) ON DELETE CASCADE,
+    token_hash VARCHAR (64) NOT NULL UNIQUE,
+    expires_on TIMESTAMPTZ NOT NULL,
+    used_on TIMESTAMPTZ,
+    created_on TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS password_reset_tokens_account_id_idx ON password_reset_tokens (account_id);
\ No newline at end of file
@@ -0,0 +1,5 @@
+-- Add up migration script here
+ALTER TABLE accounts ADD COLUMN IF NOT EXISTS email_verified_on TIMESTAMPTZ;
+
+-- Accounts created before the verification existed can keep posting
+UPDATE accounts SET email_verified_on = NOW() WHERE email_verified_on IS NULL;
\ No newline at end of file
@@ -0,0 +1,4 @@
+-- Add down migration script here
+ALTER TABLE accounts
+    DROP COLUMN IF EXISTS failed_login_attempts,
+    DROP COLUMN IF EXISTS login_locked_until;
\ No newline at end of file
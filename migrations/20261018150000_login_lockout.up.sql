@@ -0,0 +1,4 @@
+-- Add up migration script here
+ALTER TABLE accounts
+    ADD COLUMN IF NOT EXISTS failed_login_attempts integer NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS login_locked_until TIMESTAMPTZ;
\ No newline at end of file
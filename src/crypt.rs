@@ -1,9 +1,10 @@
 use argon2::{self, Config, Error as Argon2Error};
 use chrono::prelude::*;
-use paseto::{tokens, PasetoBuilder};
+use paseto::{v2::local::decrypt_paseto, PasetoBuilder};
 use rand::{thread_rng, Rng};
 use serde_json::Value;
-use std::env;
+use sha2::{Digest, Sha256};
+use std::{env, sync::OnceLock};
 use tracing::{event, Level};
 
 #[derive(Debug)]
@@ -31,6 +32,19 @@ pub fn hash_password(password: &[u8]) -> String {
     argon2::hash_encoded(password, &salt, &config).unwrap()
 }
 
+///
+/// It verifies a password against the hash of a random password and discards the result.
+///
+/// Logins of unknown emails use it, so they take as long as the logins of existing accounts and
+/// the response time does not tell whether an email has an account.
+///
+pub fn dummy_verify_password(password: &[u8]) {
+    static DUMMY_HASH: OnceLock<String> = OnceLock::new();
+
+    let hash = DUMMY_HASH.get_or_init(|| hash_password(&thread_rng().gen::<[u8; 32]>()));
+    let _ = verify_password(hash, password);
+}
+
 ///
 /// It verifies if a password equals to an encoded password (using the hash_password method).
 ///
@@ -49,47 +63,65 @@ pub fn verify_password(encoded_password: &str, password: &[u8]) -> Result<bool,
     argon2::verify_encoded(encoded_password, password)
 }
 
-pub fn encode_token(
-    claim_key: String,
-    claim_value: serde_json::value::Value,
-) -> Result<String, Error> {
+/// Creates a PASETO token valid for `duration` with the given claims.
+pub fn encode_token(claims: &[(&str, Value)], duration: chrono::Duration) -> Result<String, Error> {
     let current_date = Utc::now();
-    let one_day_duration = current_date + chrono::Duration::days(1);
+    let expiration_date = current_date + duration;
     let token_secret = env::var("AUTH_SECRET").map_err(|_| {
         event!(Level::ERROR, "AUTH_SECRET env variable is missing");
 
         Error::EncryptTokenError
     })?;
+    let encryption_key = Vec::from(token_secret.as_bytes());
+    let mut builder = PasetoBuilder::new();
+    let mut builder = builder
+        .set_encryption_key(&encryption_key)
+        .set_expiration(&expiration_date)
+        .set_not_before(&current_date);
 
-    PasetoBuilder::new()
-        .set_encryption_key(&Vec::from(token_secret.as_bytes()))
-        .set_expiration(&one_day_duration)
-        .set_not_before(&current_date)
-        .set_claim(&claim_key, claim_value)
-        .build()
-        .map_err(|err| {
-            event!(Level::ERROR, "{}", err);
-
-            Error::EncryptTokenError
-        })
+    for (claim_key, claim_value) in claims {
+        builder = builder.set_claim(claim_key, claim_value.clone());
+    }
+
+    builder.build().map_err(|err| {
+        event!(Level::ERROR, "{}", err);
+
+        Error::EncryptTokenError
+    })
 }
 
+/// Decrypts a PASETO token and returns its claims.
+///
+/// The `exp` and `nbf` claims are not validated here, so callers can tell an expired token apart
+/// from an invalid one (see `Session::validate_time`).
 pub fn decode_token(token: String) -> Result<Value, Error> {
     let token_secret = env::var("AUTH_SECRET").map_err(|_| {
         event!(Level::ERROR, "AUTH_SECRET env variable is missing");
 
         Error::EncryptTokenError
     })?;
+    let message = decrypt_paseto(&token, None, token_secret.as_bytes()).map_err(|err| {
+        event!(Level::ERROR, "{}", err);
+
+        Error::DecryptTokenError
+    })?;
 
-    tokens::validate_local_token(
-        &token,
-        None,
-        token_secret.as_bytes(),
-        &tokens::TimeBackend::Chrono,
-    )
-    .map_err(|err| {
+    serde_json::from_str(&message).map_err(|err| {
         event!(Level::ERROR, "{}", err);
 
         Error::DecryptTokenError
     })
 }
+
+/// Creates a random opaque token, used as refresh token.
+pub fn generate_token() -> String {
+    let bytes = thread_rng().gen::<[u8; 32]>();
+
+    base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+}
+
+/// Returns the SHA-256 hash (hex encoded) of an opaque token. Only the hashes are stored in the
+/// database, so a leaked database does not leak valid tokens.
+pub fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
@@ -0,0 +1,173 @@
+use chrono::Utc;
+use std::collections::HashMap;
+use tracing::{event, Level};
+use warp::{http::StatusCode, reject, reply, Rejection, Reply};
+
+use crate::store;
+use crate::types::{
+    account::{Session, Suspension, UpdateRole},
+    pagination::{extract_pagination, has_pagination, Page, Pagination},
+};
+
+/// Admins cannot suspend, ban or delete their own account, so there is always an admin left.
+fn ensure_other_account(account_id: i32, session: &Session) -> Result<(), Rejection> {
+    if account_id == session.account_id.0 {
+        return Err(reject::custom(handle_errors::Error::Unauthorized));
+    }
+
+    Ok(())
+}
+
+/// Returns a page of the accounts, filtered by the `email` query parameter (case insensitive
+/// substring). The route requires the admin role.
+///
+/// It supports the same `limit` and `offset` query parameters as the "/questions" endpoint.
+pub async fn get_accounts_handler(
+    params: HashMap<String, String>,
+    _session: Session,
+    store: store::Store,
+) -> Result<impl Reply, Rejection> {
+    let mut pagination = Pagination::default();
+    let email = params
+        .get("email")
+        .map(|email| email.trim().to_string())
+        .filter(|email| !email.is_empty());
+
+    if has_pagination(&params) {
+        event!(Level::INFO, pagination = true);
+
+        pagination = extract_pagination(params.clone())?;
+    }
+
+    if pagination.after.is_some() {
+        return Err(reject::custom(handle_errors::Error::CursorNotSupported));
+    }
+
+    let (accounts_res, total_res) = tokio::join!(
+        store.get_accounts(email.clone(), pagination.limit, pagination.offset),
+        store.count_accounts(email)
+    );
+
+    Ok(Page::new(
+        accounts_res?,
+        total_res?,
+        &pagination,
+        None,
+        "/admin/accounts",
+        &params,
+    ))
+}
+
+/// Change the role of an account. The route requires the admin role.
+///
+/// The new role applies to the next request of the account, its existing tokens included.
+pub async fn update_role_handler(
+    account_id: i32,
+    _session: Session,
+    update: UpdateRole,
+    store: store::Store,
+) -> Result<impl Reply, Rejection> {
+    match store.update_account_role(account_id, update.role).await {
+        Ok(_) => Ok(reply::with_status("Role updated!", StatusCode::OK)),
+        Err(err) => Err(reject::custom(err)),
+    }
+}
+
+/// Suspend an account until a date. The route requires the admin role.
+///
+/// Suspended accounts cannot log in and their tokens are rejected until the suspension ends.
+pub async fn suspend_account_handler(
+    account_id: i32,
+    session: Session,
+    suspension: Suspension,
+    store: store::Store,
+) -> Result<impl Reply, Rejection> {
+    ensure_other_account(account_id, &session)?;
+
+    if suspension.until <= Utc::now() {
+        return Err(reject::custom(handle_errors::Error::InvalidSuspension));
+    }
+
+    match store
+        .suspend_account(account_id, Some(suspension.until))
+        .await
+    {
+        Ok(_) => Ok(reply::with_status("Account suspended!", StatusCode::OK)),
+        Err(err) => Err(reject::custom(err)),
+    }
+}
+
+pub async fn unsuspend_account_handler(
+    account_id: i32,
+    _session: Session,
+    store: store::Store,
+) -> Result<impl Reply, Rejection> {
+    match store.suspend_account(account_id, None).await {
+        Ok(_) => Ok(reply::with_status("Suspension lifted!", StatusCode::OK)),
+        Err(err) => Err(reject::custom(err)),
+    }
+}
+
+/// Ban an account permanently. The route requires the admin role.
+pub async fn ban_account_handler(
+    account_id: i32,
+    session: Session,
+    store: store::Store,
+) -> Result<impl Reply, Rejection> {
+    ensure_other_account(account_id, &session)?;
+
+    match store.ban_account(account_id, true).await {
+        Ok(_) => Ok(reply::with_status("Account banned!", StatusCode::OK)),
+        Err(err) => Err(reject::custom(err)),
+    }
+}
+
+pub async fn unban_account_handler(
+    account_id: i32,
+    _session: Session,
+    store: store::Store,
+) -> Result<impl Reply, Rejection> {
+    match store.ban_account(account_id, false).await {
+        Ok(_) => Ok(reply::with_status("Ban lifted!", StatusCode::OK)),
+        Err(err) => Err(reject::custom(err)),
+    }
+}
+
+/// Delete an account. The route requires the admin role.
+///
+/// The questions and answers of the account are moved to the account of the required
+/// `reassign_to` query parameter.
+///
+/// # Example query
+/// `/admin/accounts/4?reassign_to=1`
+pub async fn delete_account_handler(
+    account_id: i32,
+    params: HashMap<String, String>,
+    session: Session,
+    store: store::Store,
+) -> Result<impl Reply, Rejection> {
+    ensure_other_account(account_id, &session)?;
+
+    let reassign_to = params
+        .get("reassign_to")
+        .ok_or(handle_errors::Error::MissingParameters)?
+        .parse::<i32>()
+        .map_err(handle_errors::Error::ParseError)?;
+
+    if reassign_to == account_id {
+        return Err(reject::custom(handle_errors::Error::InvalidReassignment));
+    }
+
+    match store.get_account_details(reassign_to).await {
+        Ok(_) => (),
+        Err(handle_errors::Error::AccountNotFound) => {
+            return Err(reject::custom(handle_errors::Error::InvalidReassignment))
+        }
+        Err(err) => return Err(reject::custom(err)),
+    }
+
+    match store.delete_account(account_id, reassign_to).await {
+        Ok(_) => Ok(reply::with_status("Account deleted!", StatusCode::OK)),
+        Err(err) => Err(reject::custom(err)),
+    }
+}
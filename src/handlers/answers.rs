@@ -1,26 +1,170 @@
+use std::collections::HashMap;
+use tracing::{event, Level};
 use warp::{http::StatusCode, reject, reply, Rejection, Reply};
 
-use crate::profanity;
+use crate::moderation::{self, Moderation};
 use crate::store;
-use crate::types::{account::Session, answers::NewAnswer};
+use crate::types::{
+    account::Session,
+    answers::{AnswerId, NewAnswer, UpdateAnswer},
+    flags::FlagTarget,
+    pagination::{extract_pagination, has_pagination, Page, Pagination},
+    votes::{NewVote, VoteTarget},
+};
+
+/// Returns a page of answers of a question, oldest first.
+///
+/// It supports the same `limit` and `offset` query parameters as the "/questions" endpoint.
+pub async fn get_answers_handler(
+    question_id: i32,
+    params: HashMap<String, String>,
+    store: store::Store,
+) -> Result<impl Reply, Rejection> {
+    let mut pagination = Pagination::default();
+
+    if has_pagination(&params) {
+        event!(Level::INFO, pagination = true);
+
+        pagination = extract_pagination(params.clone())?;
+    }
+
+    if pagination.after.is_some() {
+        return Err(reject::custom(handle_errors::Error::CursorNotSupported));
+    }
+
+    let (answers_res, total_res) = tokio::join!(
+        store.get_answers(question_id, pagination.limit, pagination.offset),
+        store.count_answers(question_id)
+    );
+
+    Ok(Page::new(
+        answers_res?,
+        total_res?,
+        &pagination,
+        None,
+        &format!("/questions/{}/answers", question_id),
+        &params,
+    ))
+}
 
 pub async fn add_answer_handler(
     session: Session,
     new_answer: NewAnswer,
     store: store::Store,
+    moderation: Moderation,
 ) -> Result<impl Reply, Rejection> {
-    let content = match profanity::check_profanity(new_answer.content).await {
-        Ok(censored_content) => censored_content,
+    let content = match moderation.moderate("content", new_answer.content).await {
+        Ok(moderated_content) => moderated_content,
         Err(err) => return Err(reject::custom(err)),
     };
     let answer_updated = NewAnswer {
-        content,
+        content: content.content,
         question_id: new_answer.question_id,
         account_id: session.account_id,
+        moderation_status: content.status,
     };
 
     match store.add_answer(answer_updated).await {
-        Ok(_) => Ok(reply::with_status("Answer created!", StatusCode::CREATED)),
+        Ok(answer) => {
+            moderation::flag_post(&store, FlagTarget::Answer(answer.id), content.status).await;
+
+            Ok(reply::with_status("Answer created!", StatusCode::CREATED))
+        }
+        Err(err) => Err(reject::custom(err)),
+    }
+}
+
+/// Only the author of the answer or a moderator can delete it.
+pub async fn delete_answer_handler(
+    answer_id: i32,
+    session: Session,
+    store: store::Store,
+) -> Result<impl Reply, Rejection> {
+    let can_delete = session.can_moderate()
+        || store
+            .is_answer_owner(answer_id, session.account_id.0)
+            .await?;
+
+    if !can_delete {
+        return Err(reject::custom(handle_errors::Error::Unauthorized));
+    }
+
+    match store.delete_answer(answer_id).await {
+        Ok(_) => Ok(reply::with_status("Answer deleted!", StatusCode::OK)),
+        Err(err) => Err(reject::custom(err)),
+    }
+}
+
+/// Update the content of an existing answer coming from a PUT request.
+///
+/// Only the author of the answer or a moderator can edit it, and the new content goes through the profanity service
+/// the same way a new answer does.
+pub async fn update_answer_handler(
+    answer_id: i32,
+    session: Session,
+    answer: UpdateAnswer,
+    store: store::Store,
+    moderation: Moderation,
+) -> Result<impl Reply, Rejection> {
+    let can_edit = session.can_moderate()
+        || store
+            .is_answer_owner(answer_id, session.account_id.0)
+            .await?;
+
+    if !can_edit {
+        return Err(reject::custom(handle_errors::Error::Unauthorized));
+    }
+
+    let content = match moderation.moderate("content", answer.content).await {
+        Ok(moderated_content) => moderated_content,
+        Err(err) => return Err(reject::custom(err)),
+    };
+
+    match store
+        .update_answer(
+            UpdateAnswer {
+                content: content.content,
+            },
+            answer_id,
+            content.status,
+        )
+        .await
+    {
+        Ok(answer) => {
+            moderation::flag_post(&store, FlagTarget::Answer(answer.id), content.status).await;
+
+            Ok(reply::with_status("Answer updated!", StatusCode::OK))
+        }
+        Err(err) => Err(reject::custom(err)),
+    }
+}
+
+/// Up or down vote an answer.
+///
+/// An account cannot vote its own answers and it can only vote once per answer.
+pub async fn vote_answer_handler(
+    answer_id: i32,
+    session: Session,
+    vote: NewVote,
+    store: store::Store,
+) -> Result<impl Reply, Rejection> {
+    let is_answer_owner = store
+        .is_answer_owner(answer_id, session.account_id.0)
+        .await?;
+
+    if is_answer_owner {
+        return Err(reject::custom(handle_errors::Error::CannotVoteOwnPost));
+    }
+
+    match store
+        .add_vote(
+            VoteTarget::Answer(AnswerId(answer_id)),
+            vote.direction,
+            session.account_id.0,
+        )
+        .await
+    {
+        Ok(_) => Ok(reply::with_status("Vote registered!", StatusCode::CREATED)),
         Err(err) => Err(reject::custom(err)),
     }
 }
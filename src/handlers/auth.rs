@@ -1,67 +1,347 @@
-use std::future;
+use chrono::{DateTime, Duration, Utc};
+use std::{env, future, net::IpAddr};
 use tracing::{event, Level};
-use warp::{header, reject, reply, Filter, Rejection, Reply};
+use uuid::Uuid;
+use warp::{header, http::StatusCode, reject, reply, Filter, Rejection, Reply};
 
-use crate::crypt::{decode_token, encode_token, verify_password};
+use crate::crypt::{
+    decode_token, dummy_verify_password, encode_token, generate_token, hash_token, verify_password,
+};
+use crate::handlers::verification::send_verification_email;
+use crate::lockout::Throttle;
+use crate::mail::SharedMailer;
 use crate::store::Store;
-use crate::types::account::{Credentials, NewAccount, Session};
+use crate::types::account::{
+    ensure_active, parse_bearer_token, validate_password, AccountId, Credentials, NewAccount,
+    PublicAccount, RefreshRequest, Registration, Role, Session, TokenPair,
+};
+
+const DEFAULT_ACCESS_TOKEN_TTL_MINUTES: i64 = 15;
+const DEFAULT_REFRESH_TOKEN_TTL_DAYS: i64 = 30;
+
+/// Lifetime of the access tokens, read from `ACCESS_TOKEN_TTL_MINUTES` (defaults to 15).
+fn access_token_ttl() -> Duration {
+    let minutes = env::var("ACCESS_TOKEN_TTL_MINUTES")
+        .ok()
+        .and_then(|minutes| minutes.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_ACCESS_TOKEN_TTL_MINUTES);
+
+    Duration::minutes(minutes)
+}
+
+/// Lifetime of the refresh tokens, read from `REFRESH_TOKEN_TTL_DAYS` (defaults to 30).
+fn refresh_token_ttl() -> Duration {
+    let days = env::var("REFRESH_TOKEN_TTL_DAYS")
+        .ok()
+        .and_then(|days| days.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_REFRESH_TOKEN_TTL_DAYS);
+
+    Duration::days(days)
+}
+
+/// Creates the access token of a session and returns it together with the refresh token.
+fn token_pair(
+    account_id: &AccountId,
+    role: Role,
+    session_id: Uuid,
+    refresh_token: String,
+) -> Result<TokenPair, handle_errors::Error> {
+    let ttl = access_token_ttl();
+    let access_token = encode_token(
+        &[
+            ("account_id", serde_json::json!(account_id.0)),
+            ("role", serde_json::json!(role)),
+            ("session_id", serde_json::json!(session_id)),
+        ],
+        ttl,
+    )
+    .map_err(|_| handle_errors::Error::TokenError)?;
+
+    Ok(TokenPair {
+        access_token,
+        refresh_token,
+        token_type: "Bearer",
+        expires_in: ttl.num_seconds(),
+    })
+}
+
+/// Starts a new session of an account and returns its tokens.
+async fn start_session(
+    account_id: &AccountId,
+    role: Role,
+    store: &Store,
+) -> Result<TokenPair, handle_errors::Error> {
+    let refresh_token = generate_token();
+    let session_id = store
+        .add_session(
+            account_id.0,
+            hash_token(&refresh_token),
+            Utc::now() + refresh_token_ttl(),
+        )
+        .await?;
+
+    token_pair(account_id, role, session_id, refresh_token)
+}
 
 /// Handler responsible to register a new account to the database.
-pub async fn register(new_account: NewAccount, store: Store) -> Result<impl Reply, Rejection> {
-    match store.add_account(new_account).await {
-        Ok(account) => Ok(reply::json(&account)),
+///
+/// It replies with the public profile of the account and the tokens of its first session, so the
+/// account does not need to log in after the registration. The account cannot post until its email
+/// is verified with the link sent to it.
+pub async fn register(
+    new_account: NewAccount,
+    store: Store,
+    mailer: SharedMailer,
+) -> Result<impl Reply, Rejection> {
+    validate_password(&new_account.password, Some(&new_account.email))?;
+
+    let account = store.add_account(new_account).await?;
+
+    if let Err(err) = send_verification_email(&account.id, &account.email, &mailer).await {
+        event!(
+            Level::ERROR,
+            "Could not send the verification email: {}",
+            err
+        );
+    }
+
+    match start_session(&account.id, account.role, &store).await {
+        Ok(tokens) => Ok(reply::json(&Registration {
+            account: PublicAccount::from(account),
+            tokens,
+        })),
         Err(err) => Err(reject::custom(err)),
     }
 }
 
-pub async fn login(credentials: Credentials, store: Store) -> Result<impl Reply, Rejection> {
-    match store.get_account(credentials.email).await {
-        Ok(account) => match verify_password(&account.password, credentials.password.as_bytes()) {
-            Ok(verified) => {
-                if verified {
-                    let token_result =
-                        encode_token(String::from("account_id"), serde_json::json!(account.id.0));
-
-                    match token_result {
-                        Ok(token) => Ok(reply::json(&token)),
-                        Err(_) => Err(reject::custom(handle_errors::Error::TokenError)),
-                    }
-                } else {
-                    Err(reject::custom(handle_errors::Error::WrongPassword))
+/// Extracts the IP of the client, see `LoginThrottle::client_ip`.
+pub fn client_ip(
+    throttle: Throttle,
+) -> impl Filter<Extract = (Option<IpAddr>,), Error = Rejection> + Clone {
+    header::optional::<String>("X-Forwarded-For")
+        .and(warp::addr::remote())
+        .map(move |forwarded_for: Option<String>, remote| {
+            throttle.client_ip(forwarded_for.as_deref(), remote)
+        })
+}
+
+/// Handler responsible to log in an account with its email and password.
+///
+/// Unknown emails, wrong passwords and locked accounts are all rejected with
+/// `Error::WrongPassword` after verifying a password, so neither the response nor its timing tell
+/// whether an email has an account. Accounts and IPs with too many consecutive failed logins are
+/// locked for an exponentially growing time.
+pub async fn login(
+    ip: Option<IpAddr>,
+    credentials: Credentials,
+    store: Store,
+    throttle: Throttle,
+) -> Result<impl Reply, Rejection> {
+    let now = Utc::now();
+
+    if let Some(ip) = ip {
+        throttle.check_ip(ip, now)?;
+    }
+
+    let account = store
+        .get_account(credentials.email)
+        .await?
+        .filter(|account| {
+            if account.is_login_locked(now) {
+                event!(Level::WARN, "Login of the locked account {}", account.id.0);
+            }
+
+            !account.is_login_locked(now)
+        });
+    let account = match account {
+        Some(account) => {
+            match verify_password(account.password.as_str(), credentials.password.as_bytes()) {
+                Ok(true) => Some(account),
+                Ok(false) => {
+                    record_wrong_password(&store, &throttle, account.id.0, now).await?;
+
+                    None
+                }
+                Err(err) => {
+                    return Err(reject::custom(handle_errors::Error::ArgonLibraryError(err)))
                 }
             }
-            Err(err) => Err(reject::custom(handle_errors::Error::ArgonLibraryError(err))),
-        },
+        }
+        None => {
+            dummy_verify_password(credentials.password.as_bytes());
+
+            None
+        }
+    };
+    let account = match account {
+        Some(account) => account,
+        None => {
+            if let Some(ip) = ip {
+                throttle.record_ip_failure(ip, now);
+            }
+
+            return Err(reject::custom(handle_errors::Error::WrongPassword));
+        }
+    };
+
+    store.reset_failed_logins(account.id.0).await?;
+    ensure_active(account.suspended_until, account.banned_on, now)?;
+
+    match start_session(&account.id, account.role, &store).await {
+        Ok(tokens) => Ok(reply::json(&tokens)),
         Err(err) => Err(reject::custom(err)),
     }
 }
 
-pub fn verify_token(token: String) -> Result<Session, handle_errors::Error> {
-    match decode_token(token) {
+/// Counts a wrong password of an account, locking its logins after too many consecutive wrong
+/// passwords.
+pub async fn record_wrong_password(
+    store: &Store,
+    throttle: &Throttle,
+    account_id: i32,
+    now: DateTime<Utc>,
+) -> Result<(), handle_errors::Error> {
+    let failed_attempts = store.record_failed_login(account_id).await?;
+
+    if let Some(lockout) = throttle.account_lockout(failed_attempts as u32) {
+        event!(
+            Level::WARN,
+            "Too many failed logins, the account {} is locked for {} seconds",
+            account_id,
+            lockout.num_seconds()
+        );
+
+        store.lock_login(account_id, now + lockout).await?;
+    }
+
+    Ok(())
+}
+
+/// Exchanges a refresh token for a new pair of tokens. The refresh token cannot be used again.
+///
+/// The role of the new access token is read from the database, so role changes apply from the
+/// next refresh.
+pub async fn refresh(request: RefreshRequest, store: Store) -> Result<impl Reply, Rejection> {
+    let refresh_token = generate_token();
+    let (session_id, account_id) = store
+        .rotate_refresh_token(
+            hash_token(&request.refresh_token),
+            hash_token(&refresh_token),
+            Utc::now() + refresh_token_ttl(),
+        )
+        .await?;
+    let account = store.get_account_details(account_id).await?;
+
+    ensure_active(account.suspended_until, account.banned_on, Utc::now())?;
+
+    match token_pair(&account.id, account.role, session_id, refresh_token) {
+        Ok(tokens) => Ok(reply::json(&tokens)),
+        Err(err) => Err(reject::custom(err)),
+    }
+}
+
+/// Revokes the session of the access token, so neither its access nor its refresh tokens can be
+/// used anymore.
+pub async fn logout(session: Session, store: Store) -> Result<impl Reply, Rejection> {
+    match store.revoke_session(session.session_id).await {
+        Ok(_) => Ok(reply::with_status("Logged out!", StatusCode::OK)),
+        Err(err) => Err(reject::custom(err)),
+    }
+}
+
+/// Decodes an access token and checks that its session has not been revoked.
+pub async fn verify_token(token: String, store: &Store) -> Result<Session, handle_errors::Error> {
+    let session = match decode_token(token) {
         Ok(value) => serde_json::from_value::<Session>(value).map_err(|err| {
             event!(Level::ERROR, "{}", err);
 
             handle_errors::Error::TokenError
-        }),
+        })?,
         Err(err) => {
             event!(Level::ERROR, "{}", err);
 
-            Err(handle_errors::Error::TokenError)
+            return Err(handle_errors::Error::TokenError);
         }
+    };
+
+    session.validate_time(Utc::now())?;
+
+    if !store.is_session_active(session.session_id).await? {
+        event!(Level::ERROR, "Session {} is revoked", session.session_id);
+
+        return Err(handle_errors::Error::TokenError);
     }
+
+    Ok(session)
+}
+
+/// Extracts the session from the `Authorization: Bearer <token>` header.
+///
+/// The session and the account are read from the database on every request, so revoked sessions
+/// and suspended, banned or deleted accounts are rejected even if their token has not expired. The
+/// role of the session is the current role of the account, not the one of the token.
+pub fn auth(store: Store) -> impl Filter<Extract = (Session,), Error = Rejection> + Clone {
+    authenticate(store, false)
+}
+
+/// Same as `auth` but it also rejects the accounts without a verified email with
+/// `Error::EmailNotVerified`. It is used by the routes posting questions and answers.
+pub fn verified_auth(store: Store) -> impl Filter<Extract = (Session,), Error = Rejection> + Clone {
+    authenticate(store, true)
 }
 
-pub fn auth() -> impl Filter<Extract = (Session,), Error = Rejection> + Clone {
-    header::<String>("Authorization").and_then(|token: String| {
-        let session_result = verify_token(token);
-        let session = match session_result {
-            Ok(session) => session,
-            Err(err) => {
-                event!(Level::ERROR, "{}", err);
+fn authenticate(
+    store: Store,
+    require_verified_email: bool,
+) -> impl Filter<Extract = (Session,), Error = Rejection> + Clone {
+    header::optional::<String>("Authorization").and_then(move |authorization: Option<String>| {
+        let store = store.clone();
+
+        async move {
+            let authorization = authorization.ok_or(handle_errors::Error::MissingToken)?;
+            let token = parse_bearer_token(&authorization)?;
+            let mut session = verify_token(token.to_string(), &store).await?;
+            let account = match store.get_account_details(session.account_id.0).await {
+                Ok(account) => account,
+                Err(handle_errors::Error::AccountNotFound) => {
+                    event!(Level::ERROR, "Account {} not found", session.account_id.0);
+
+                    return Err(reject::custom(handle_errors::Error::TokenError));
+                }
+                Err(err) => return Err(reject::custom(err)),
+            };
+
+            ensure_active(account.suspended_until, account.banned_on, Utc::now())?;
 
-                return future::ready(Err(reject::custom(handle_errors::Error::TokenError)));
+            if require_verified_email && account.email_verified_on.is_none() {
+                return Err(reject::custom(handle_errors::Error::EmailNotVerified));
             }
-        };
+
+            // The role of the token can be outdated, role changes apply to the next request
+            session.role = account.role;
+
+            Ok(session)
+        }
+    })
+}
+
+/// Same as `auth` but it also requires the account of the session to have at least `min_role`,
+/// otherwise the request is rejected with `Error::Unauthorized`.
+pub fn require_role(
+    store: Store,
+    min_role: Role,
+) -> impl Filter<Extract = (Session,), Error = Rejection> + Clone {
+    auth(store).and_then(move |session: Session| {
+        if session.role < min_role {
+            event!(
+                Level::ERROR,
+                "Account {} does not have the {:?} role",
+                session.account_id.0,
+                min_role
+            );
+
+            return future::ready(Err(reject::custom(handle_errors::Error::Unauthorized)));
+        }
 
         future::ready(Ok(session))
     })
@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+use tracing::{event, Level};
+use warp::{http::StatusCode, reject, reply, Rejection, Reply};
+
+use crate::store;
+use crate::types::{
+    account::Session,
+    answers::AnswerId,
+    flags::{extract_flag_status, FlagAction, FlagStatus, FlagTarget, NewFlag, ReviewFlag},
+    pagination::{extract_pagination, has_pagination, Page, Pagination},
+    questions::QuestionId,
+};
+
+/// Report a question to the moderators. An account can only flag a question once.
+pub async fn flag_question_handler(
+    question_id: i32,
+    session: Session,
+    new_flag: NewFlag,
+    store: store::Store,
+) -> Result<impl Reply, Rejection> {
+    match store
+        .add_flag(
+            FlagTarget::Question(QuestionId(question_id)),
+            new_flag,
+            Some(session.account_id.0),
+        )
+        .await
+    {
+        Ok(_) => Ok(reply::with_status("Flag created!", StatusCode::CREATED)),
+        Err(err) => Err(reject::custom(err)),
+    }
+}
+
+/// Report an answer to the moderators. An account can only flag an answer once.
+pub async fn flag_answer_handler(
+    answer_id: i32,
+    session: Session,
+    new_flag: NewFlag,
+    store: store::Store,
+) -> Result<impl Reply, Rejection> {
+    match store
+        .add_flag(
+            FlagTarget::Answer(AnswerId(answer_id)),
+            new_flag,
+            Some(session.account_id.0),
+        )
+        .await
+    {
+        Ok(_) => Ok(reply::with_status("Flag created!", StatusCode::CREATED)),
+        Err(err) => Err(reject::custom(err)),
+    }
+}
+
+/// Returns a page of the moderation queue, oldest flags first. The route requires the moderator role.
+///
+/// It supports the same `limit` and `offset` query parameters as the "/questions" endpoint, and
+/// a `status` parameter (open by default).
+pub async fn get_flags_handler(
+    params: HashMap<String, String>,
+    _session: Session,
+    store: store::Store,
+) -> Result<impl Reply, Rejection> {
+    let mut pagination = Pagination::default();
+    let status = extract_flag_status(&params)?;
+
+    if has_pagination(&params) {
+        event!(Level::INFO, pagination = true);
+
+        pagination = extract_pagination(params.clone())?;
+    }
+
+    if pagination.after.is_some() {
+        return Err(reject::custom(handle_errors::Error::CursorNotSupported));
+    }
+
+    let (flags_res, total_res) = tokio::join!(
+        store.get_flags(status, pagination.limit, pagination.offset),
+        store.count_flags(status)
+    );
+
+    Ok(Page::new(
+        flags_res?,
+        total_res?,
+        &pagination,
+        None,
+        "/moderation/flags",
+        &params,
+    ))
+}
+
+/// Resolve or dismiss a flag, or delete the flagged post. The route requires the moderator role.
+///
+/// Resolving or dismissing a flag closes every open flag of the same post.
+pub async fn review_flag_handler(
+    flag_id: i32,
+    session: Session,
+    review: ReviewFlag,
+    store: store::Store,
+) -> Result<impl Reply, Rejection> {
+    let target = store.get_flag_target(flag_id).await?;
+    let status = match review.action {
+        FlagAction::Resolve => FlagStatus::Resolved,
+        FlagAction::Dismiss => FlagStatus::Dismissed,
+        FlagAction::DeleteTarget => {
+            // Flags are deleted together with the post
+            let res = match target {
+                FlagTarget::Question(question_id) => store.delete_question(question_id.0).await,
+                FlagTarget::Answer(answer_id) => store.delete_answer(answer_id.0).await,
+            };
+
+            return match res {
+                Ok(_) => Ok(reply::with_status("Post deleted!", StatusCode::OK)),
+                Err(err) => Err(reject::custom(err)),
+            };
+        }
+    };
+
+    match store
+        .review_flags(target, status, session.account_id.0)
+        .await
+    {
+        Ok(_) => Ok(reply::with_status("Flag reviewed!", StatusCode::OK)),
+        Err(err) => Err(reject::custom(err)),
+    }
+}
@@ -0,0 +1,37 @@
+use serde::Serialize;
+use std::sync::Arc;
+use warp::{reply, Rejection, Reply};
+
+use crate::moderation::{FailurePolicy, Moderation, ModerationMode};
+use crate::profanity::{
+    cache::CacheHealth, circuit_breaker::CircuitBreakerHealth, CircuitBreaker, ProfanityCache,
+};
+
+#[derive(Debug, Serialize)]
+struct ModerationHealth {
+    circuit_breaker: CircuitBreakerHealth,
+    cache: CacheHealth,
+    failure_policy: FailurePolicy,
+    mode: ModerationMode,
+}
+
+#[derive(Debug, Serialize)]
+struct Health {
+    moderation: ModerationHealth,
+}
+
+/// Reports the state of the external services the API depends on.
+pub async fn health_handler(
+    circuit_breaker: Arc<CircuitBreaker>,
+    profanity_cache: Arc<ProfanityCache>,
+    moderation: Moderation,
+) -> Result<impl Reply, Rejection> {
+    Ok(reply::json(&Health {
+        moderation: ModerationHealth {
+            circuit_breaker: circuit_breaker.health(),
+            cache: profanity_cache.health(),
+            failure_policy: moderation.failure_policy(),
+            mode: moderation.mode(),
+        },
+    }))
+}
@@ -1,3 +1,10 @@
+pub mod accounts;
 pub mod answers;
 pub mod auth;
+pub mod flags;
+pub mod health;
+pub mod password;
 pub mod questions;
+pub mod search;
+pub mod tags;
+pub mod verification;
@@ -0,0 +1,154 @@
+use chrono::{Duration, Utc};
+use std::{env, net::IpAddr};
+use tracing::{event, Level};
+use warp::{http::StatusCode, reject, reply, Rejection, Reply};
+
+use crate::crypt::{generate_token, hash_token, verify_password};
+use crate::handlers::auth::record_wrong_password;
+use crate::lockout::Throttle;
+use crate::mail::{SharedMailer, Template};
+use crate::store::Store;
+use crate::types::account::{
+    validate_password, ChangePassword, PasswordResetConfirm, PasswordResetRequest, Session,
+};
+
+const DEFAULT_PASSWORD_RESET_TTL_MINUTES: i64 = 60;
+const DEFAULT_PASSWORD_RESET_URL: &str = "http://localhost:3030/password-reset";
+
+/// Lifetime of the password reset tokens, read from `PASSWORD_RESET_TTL_MINUTES` (defaults to 60).
+fn password_reset_ttl() -> Duration {
+    let minutes = env::var("PASSWORD_RESET_TTL_MINUTES")
+        .ok()
+        .and_then(|minutes| minutes.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_PASSWORD_RESET_TTL_MINUTES);
+
+    Duration::minutes(minutes)
+}
+
+/// Page where users choose their new password, read from `PASSWORD_RESET_URL`. The reset token is
+/// sent as its `token` query parameter.
+fn password_reset_url(token: &str) -> String {
+    let url = env::var("PASSWORD_RESET_URL").unwrap_or(String::from(DEFAULT_PASSWORD_RESET_URL));
+
+    format!("{}?token={}", url, token)
+}
+
+/// Changes the password of the account of the session. The other sessions of the account are
+/// revoked.
+///
+/// A wrong current password counts as a failed login of the account and of the client IP, so a
+/// stolen session cannot be used to guess the password.
+pub async fn change_password_handler(
+    session: Session,
+    ip: Option<IpAddr>,
+    change: ChangePassword,
+    store: Store,
+    throttle: Throttle,
+) -> Result<impl Reply, Rejection> {
+    let now = Utc::now();
+
+    if let Some(ip) = ip {
+        throttle.check_ip(ip, now)?;
+    }
+
+    let details = store.get_account_details(session.account_id.0).await?;
+    let account = match store.get_account(details.email).await? {
+        Some(account) => account,
+        None => return Err(reject::custom(handle_errors::Error::AccountNotFound)),
+    };
+
+    if let Some(until) = account.login_locked_until.filter(|until| *until > now) {
+        return Err(reject::custom(handle_errors::Error::TooManyLoginAttempts(
+            (until - now).num_seconds().max(1),
+        )));
+    }
+
+    match verify_password(
+        account.password.as_str(),
+        change.current_password.as_bytes(),
+    ) {
+        Ok(true) => (),
+        Ok(false) => {
+            record_wrong_password(&store, &throttle, account.id.0, now).await?;
+
+            if let Some(ip) = ip {
+                throttle.record_ip_failure(ip, now);
+            }
+
+            return Err(reject::custom(handle_errors::Error::WrongPassword));
+        }
+        Err(err) => return Err(reject::custom(handle_errors::Error::ArgonLibraryError(err))),
+    }
+
+    validate_password(&change.new_password, Some(&account.email))?;
+
+    match store
+        .update_account_password(
+            session.account_id.0,
+            change.new_password,
+            Some(session.session_id),
+        )
+        .await
+    {
+        Ok(_) => Ok(reply::with_status("Password updated!", StatusCode::OK)),
+        Err(err) => Err(reject::custom(err)),
+    }
+}
+
+/// Sends a password reset link to the email of an account.
+///
+/// It replies the same whether the email belongs to an account or not, so it cannot be used to
+/// find out the emails of the accounts.
+pub async fn request_password_reset_handler(
+    request: PasswordResetRequest,
+    store: Store,
+    mailer: SharedMailer,
+) -> Result<impl Reply, Rejection> {
+    if let Some(account_id) = store.find_account_id(request.email.clone()).await? {
+        let token = generate_token();
+
+        store
+            .add_password_reset_token(
+                account_id,
+                hash_token(&token),
+                Utc::now() + password_reset_ttl(),
+            )
+            .await?;
+
+        let email = Template::PasswordReset.render(
+            &request.email,
+            &[
+                ("url", &password_reset_url(&token)),
+                ("minutes", &password_reset_ttl().num_minutes().to_string()),
+            ],
+        );
+
+        if let Err(err) = mailer.send(email).await {
+            event!(
+                Level::ERROR,
+                "Could not send the password reset email: {}",
+                err
+            );
+        }
+    }
+
+    Ok(reply::with_status(
+        "Password reset requested!",
+        StatusCode::OK,
+    ))
+}
+
+/// Sets a new password using a password reset token. The token and the other reset tokens of the
+/// account cannot be used again and every session of the account is revoked.
+pub async fn confirm_password_reset_handler(
+    confirm: PasswordResetConfirm,
+    store: Store,
+) -> Result<impl Reply, Rejection> {
+    match store
+        .reset_password(hash_token(&confirm.token), confirm.password)
+        .await
+    {
+        Ok(_) => Ok(reply::with_status("Password reset!", StatusCode::OK)),
+        Err(err) => Err(reject::custom(err)),
+    }
+}
@@ -3,68 +3,125 @@ use tracing::event;
 use tracing::Level;
 use warp::{http::StatusCode, reject, reply, Rejection, Reply};
 
-use crate::profanity;
+use crate::moderation::{self, Moderation};
 use crate::store;
 use crate::types::{
     account::Session,
-    pagination::{extract_pagination, Pagination},
-    questions::{NewQuestion, Question},
+    flags::FlagTarget,
+    pagination::{extract_pagination, has_pagination, Cursor, Page, Pagination},
+    questions::{extract_sort, NewQuestion, Question, QuestionId},
+    tags::{extract_tag_filter, normalize_tags},
+    votes::{NewVote, VoteTarget},
 };
 
+/// Returns a page of questions, using offset pagination (`limit` and `offset`) or cursor pagination
+/// (`after` and `limit`).
+///
+/// The response is a `Page` envelope, which contains the cursor of the next page when using cursor
+/// pagination.
 pub async fn get_questions_handler(
     params: HashMap<String, String>,
     store: store::Store,
 ) -> Result<impl Reply, Rejection> {
     let mut pagination = Pagination::default();
+    let sort = extract_sort(&params)?;
+    let tag_filter = extract_tag_filter(&params)?;
 
-    if !params.is_empty() {
+    if has_pagination(&params) {
         event!(tracing::Level::INFO, pagination = true);
 
-        pagination = extract_pagination(params)?;
+        pagination = extract_pagination(params.clone())?;
     }
 
-    match store
-        .get_questions(pagination.limit, pagination.offset)
-        .await
-    {
-        Ok(res) => Ok(reply::json(&res)),
-        Err(err) => Err(reject::custom(err)),
+    if pagination.after.is_some() && !sort.supports_cursor() {
+        return Err(reject::custom(handle_errors::Error::CursorNotSupported));
+    }
+
+    // In cursor pagination one extra question is requested to know if there is a next page
+    let fetch_limit = match pagination.after {
+        Some(_) => pagination.limit.map(|limit| limit.saturating_add(1)),
+        None => pagination.limit,
+    };
+    let (questions_res, total_res) = tokio::join!(
+        store.get_questions(
+            fetch_limit,
+            pagination.offset,
+            sort,
+            tag_filter.clone(),
+            pagination.after.clone(),
+        ),
+        store.count_questions(sort, tag_filter)
+    );
+    let mut questions = questions_res?;
+    let total = total_res?;
+    let mut next_cursor = None;
+
+    if let (Some(_), Some(limit)) = (&pagination.after, pagination.limit) {
+        if questions.len() > limit as usize {
+            questions.truncate(limit as usize);
+
+            next_cursor = questions.last().map(|question| {
+                Cursor::After {
+                    created_on: question.created_on,
+                    id: question.id.0,
+                }
+                .encode()
+            });
+        }
     }
+
+    Ok(Page::new(
+        questions,
+        total,
+        &pagination,
+        next_cursor,
+        "/questions",
+        &params,
+    ))
 }
 
 /// Add a new question coming from a POST request to the database.
 ///
-/// It checks if title or the content of the question contain a censored content. We use tokio::spawn for executing the different
-/// calls to the profanity service in parallel.
+/// Tags are normalized before storing them (see `normalize_tags`).
+/// It checks if title or the content of the question contain a censored content. The title and the content are sent to the
+/// profanity service in parallel. When the service is unavailable the failure policy of the moderation decides
+/// if the question is rejected, published or held for review.
 pub async fn add_question_handler(
     session: Session,
     new_question: NewQuestion,
     store: store::Store,
+    moderation: Moderation,
 ) -> Result<impl Reply, Rejection> {
-    let get_title_task = tokio::spawn(profanity::check_profanity(new_question.title));
-    let get_content_task = tokio::spawn(profanity::check_profanity(new_question.content));
-    let (title_res, content_res) = (
-        get_title_task.await.unwrap(),
-        get_content_task.await.unwrap(),
-    );
-
-    if title_res.is_err() {
-        return Err(reject::custom(title_res.unwrap_err()));
-    };
-
-    if content_res.is_err() {
-        return Err(reject::custom(content_res.unwrap_err()));
+    let tags = normalize_tags(new_question.tags)?;
+    let (title, content) = match moderation
+        .moderate_question(new_question.title, new_question.content)
+        .await
+    {
+        Ok(moderated) => moderated,
+        Err(err) => return Err(reject::custom(err)),
     };
 
     let question = NewQuestion {
-        title: title_res.unwrap(),
-        content: content_res.unwrap(),
-        tags: new_question.tags,
+        title: title.content,
+        content: content.content,
+        tags,
         account_id: session.account_id,
+        moderation_status: title.status.max(content.status),
     };
 
+    let moderation_status = question.moderation_status;
+
     match store.add_question(question).await {
-        Ok(question) => Ok(reply::json(&question)),
+        Ok(question) => {
+            moderation::flag_post(
+                &store,
+                FlagTarget::Question(question.id.clone()),
+                moderation_status,
+            )
+            .await;
+
+            Ok(reply::json(&question))
+        }
         Err(err) => {
             event!(Level::ERROR, "{}", err);
 
@@ -83,16 +140,18 @@ pub async fn get_question_handler(
     }
 }
 
+/// Only the author of the question or a moderator can delete it.
 pub async fn delete_question_handler(
     question_id: i32,
     session: Session,
     store: store::Store,
 ) -> Result<impl Reply, Rejection> {
-    let is_question_owner = store
-        .is_question_owner(question_id, session.account_id.0)
-        .await?;
+    let can_delete = session.can_moderate()
+        || store
+            .is_question_owner(question_id, session.account_id.0)
+            .await?;
 
-    if !is_question_owner {
+    if !can_delete {
         return Err(reject::custom(handle_errors::Error::Unauthorized));
     }
 
@@ -104,6 +163,9 @@ pub async fn delete_question_handler(
 
 /// Update an existiong question coming from a PUT request.
 ///
+/// Only the author of the question or a moderator can edit it.
+///
+/// Tags are normalized before storing them (see `normalize_tags`).
 /// It checks if title or the content of the question contain a censored content. We use tokio::join for executing the different
 /// calls to the profanity service concurrenty.
 pub async fn update_question_handler(
@@ -111,6 +173,60 @@ pub async fn update_question_handler(
     session: Session,
     question: Question,
     store: store::Store,
+    moderation: Moderation,
+) -> Result<impl Reply, Rejection> {
+    let can_edit = session.can_moderate()
+        || store
+            .is_question_owner(question_id, session.account_id.0)
+            .await?;
+
+    if !can_edit {
+        return Err(reject::custom(handle_errors::Error::Unauthorized));
+    }
+
+    let tags = normalize_tags(question.tags)?;
+    let (title, content) = match moderation
+        .moderate_question(question.title, question.content)
+        .await
+    {
+        Ok(moderated) => moderated,
+        Err(err) => return Err(reject::custom(err)),
+    };
+
+    let moderation_status = title.status.max(content.status);
+    let question_updated = Question {
+        id: question.id,
+        title: title.content,
+        content: content.content,
+        tags,
+        accepted_answer: question.accepted_answer,
+        score: question.score,
+        created_on: question.created_on,
+    };
+
+    match store
+        .update_question(question_updated, question_id, moderation_status)
+        .await
+    {
+        Ok(question) => {
+            moderation::flag_post(&store, FlagTarget::Question(question.id), moderation_status)
+                .await;
+
+            Ok(reply::with_status("Question updated!", StatusCode::OK))
+        }
+        Err(err) => Err(reject::custom(err)),
+    }
+}
+
+/// Mark an answer as the accepted one for a question.
+///
+/// Only the author of the question can accept an answer, and the answer has to belong to that question.
+/// Accepting a different answer replaces the previous one.
+pub async fn accept_answer_handler(
+    question_id: i32,
+    answer_id: i32,
+    session: Session,
+    store: store::Store,
 ) -> Result<impl Reply, Rejection> {
     let is_question_owner = store
         .is_question_owner(question_id, session.account_id.0)
@@ -120,27 +236,57 @@ pub async fn update_question_handler(
         return Err(reject::custom(handle_errors::Error::Unauthorized));
     }
 
-    let title_task = profanity::check_profanity(question.title);
-    let content_task = profanity::check_profanity(question.content);
-    let (title_res, content_res) = tokio::join!(title_task, content_task);
+    match store.accept_answer(question_id, answer_id).await {
+        Ok(_) => Ok(reply::with_status("Answer accepted!", StatusCode::OK)),
+        Err(err) => Err(reject::custom(err)),
+    }
+}
 
-    if title_res.is_err() {
-        return Err(reject::custom(title_res.unwrap_err()));
-    };
+pub async fn unaccept_answer_handler(
+    question_id: i32,
+    session: Session,
+    store: store::Store,
+) -> Result<impl Reply, Rejection> {
+    let is_question_owner = store
+        .is_question_owner(question_id, session.account_id.0)
+        .await?;
 
-    if content_res.is_err() {
-        return Err(reject::custom(content_res.unwrap_err()));
-    };
+    if !is_question_owner {
+        return Err(reject::custom(handle_errors::Error::Unauthorized));
+    }
 
-    let question_updated = Question {
-        id: question.id,
-        title: title_res.unwrap(),
-        content: content_res.unwrap(),
-        tags: question.tags,
-    };
+    match store.unaccept_answer(question_id).await {
+        Ok(_) => Ok(reply::with_status("Answer unaccepted!", StatusCode::OK)),
+        Err(err) => Err(reject::custom(err)),
+    }
+}
+
+/// Up or down vote a question.
+///
+/// An account cannot vote its own questions and it can only vote once per question.
+pub async fn vote_question_handler(
+    question_id: i32,
+    session: Session,
+    vote: NewVote,
+    store: store::Store,
+) -> Result<impl Reply, Rejection> {
+    let is_question_owner = store
+        .is_question_owner(question_id, session.account_id.0)
+        .await?;
 
-    match store.update_question(question_updated, question_id).await {
-        Ok(_) => Ok(reply::with_status("Question updated!", StatusCode::OK)),
+    if is_question_owner {
+        return Err(reject::custom(handle_errors::Error::CannotVoteOwnPost));
+    }
+
+    match store
+        .add_vote(
+            VoteTarget::Question(QuestionId(question_id)),
+            vote.direction,
+            session.account_id.0,
+        )
+        .await
+    {
+        Ok(_) => Ok(reply::with_status("Vote registered!", StatusCode::CREATED)),
         Err(err) => Err(reject::custom(err)),
     }
 }
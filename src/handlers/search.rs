@@ -0,0 +1,45 @@
+use std::collections::HashMap;
+use tracing::{event, Level};
+use warp::{reject, Rejection, Reply};
+
+use crate::store;
+use crate::types::{
+    pagination::{extract_pagination, has_pagination, Page, Pagination},
+    search::extract_search_query,
+};
+
+/// Full-text search over question titles, question contents and answer contents.
+///
+/// Results are sorted by relevance and they support the same `limit` and `offset` query
+/// parameters as the "/questions" endpoint.
+pub async fn search_handler(
+    params: HashMap<String, String>,
+    store: store::Store,
+) -> Result<impl Reply, Rejection> {
+    let mut pagination = Pagination::default();
+    let query = extract_search_query(&params)?;
+
+    if has_pagination(&params) {
+        event!(Level::INFO, pagination = true);
+
+        pagination = extract_pagination(params.clone())?;
+    }
+
+    if pagination.after.is_some() {
+        return Err(reject::custom(handle_errors::Error::CursorNotSupported));
+    }
+
+    let (results_res, total_res) = tokio::join!(
+        store.search(query.clone(), pagination.limit, pagination.offset),
+        store.count_search_results(query)
+    );
+
+    Ok(Page::new(
+        results_res?,
+        total_res?,
+        &pagination,
+        None,
+        "/search",
+        &params,
+    ))
+}
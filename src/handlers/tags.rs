@@ -0,0 +1,60 @@
+use std::collections::HashMap;
+use tracing::{event, Level};
+use warp::{reject, reply, Rejection, Reply};
+
+use crate::store;
+use crate::types::{
+    account::Session,
+    pagination::{extract_pagination, has_pagination, Page, Pagination},
+    tags::{normalize_tag_param, UpdateTag},
+};
+
+/// Returns a page of the tag catalog with the number of questions using each tag, the most used
+/// tags first.
+pub async fn get_tags_handler(
+    params: HashMap<String, String>,
+    store: store::Store,
+) -> Result<impl Reply, Rejection> {
+    let mut pagination = Pagination::default();
+
+    if has_pagination(&params) {
+        event!(Level::INFO, pagination = true);
+
+        pagination = extract_pagination(params.clone())?;
+    }
+
+    if pagination.after.is_some() {
+        return Err(reject::custom(handle_errors::Error::CursorNotSupported));
+    }
+
+    let (tags_res, total_res) = tokio::join!(
+        store.get_tags(pagination.limit, pagination.offset),
+        store.count_tags()
+    );
+
+    Ok(Page::new(
+        tags_res?,
+        total_res?,
+        &pagination,
+        None,
+        "/tags",
+        &params,
+    ))
+}
+
+/// Update the description of a tag of the catalog.
+///
+/// The catalog is shared by every account, so the route requires the moderator role.
+pub async fn update_tag_handler(
+    name: String,
+    _session: Session,
+    tag: UpdateTag,
+    store: store::Store,
+) -> Result<impl Reply, Rejection> {
+    let name = normalize_tag_param(&name)?;
+
+    match store.update_tag(tag, name).await {
+        Ok(tag) => Ok(reply::json(&tag)),
+        Err(err) => Err(reject::custom(err)),
+    }
+}
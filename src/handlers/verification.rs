@@ -0,0 +1,123 @@
+use chrono::{Duration, Utc};
+use std::{collections::HashMap, env};
+use tracing::{event, Level};
+use warp::{http::StatusCode, reject, reply, Rejection, Reply};
+
+use crate::crypt::{decode_token, encode_token};
+use crate::mail::{SharedMailer, Template};
+use crate::store::Store;
+use crate::types::account::{AccountId, EmailVerification, Session, EMAIL_VERIFICATION_PURPOSE};
+
+const DEFAULT_EMAIL_VERIFICATION_TTL_HOURS: i64 = 24;
+const DEFAULT_EMAIL_VERIFICATION_URL: &str = "http://localhost:3030/email-verification";
+
+/// Lifetime of the email verification links, read from `EMAIL_VERIFICATION_TTL_HOURS` (defaults
+/// to 24).
+fn email_verification_ttl() -> Duration {
+    let hours = env::var("EMAIL_VERIFICATION_TTL_HOURS")
+        .ok()
+        .and_then(|hours| hours.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_EMAIL_VERIFICATION_TTL_HOURS);
+
+    Duration::hours(hours)
+}
+
+/// Email verification link, read from `EMAIL_VERIFICATION_URL`. The signed token is sent as its
+/// `token` query parameter.
+fn email_verification_url(token: &str) -> String {
+    let url =
+        env::var("EMAIL_VERIFICATION_URL").unwrap_or(String::from(DEFAULT_EMAIL_VERIFICATION_URL));
+
+    format!("{}?token={}", url, token)
+}
+
+/// Sends the verification link of an email. The link carries a signed token with the account and
+/// the email, so nothing needs to be stored until the email is verified.
+pub async fn send_verification_email(
+    account_id: &AccountId,
+    email: &str,
+    mailer: &SharedMailer,
+) -> Result<(), handle_errors::Error> {
+    let ttl = email_verification_ttl();
+    let token = encode_token(
+        &[
+            ("account_id", serde_json::json!(account_id.0)),
+            ("email", serde_json::json!(email)),
+            ("purpose", serde_json::json!(EMAIL_VERIFICATION_PURPOSE)),
+        ],
+        ttl,
+    )
+    .map_err(|_| handle_errors::Error::TokenError)?;
+
+    mailer
+        .send(Template::EmailVerification.render(
+            email,
+            &[
+                ("url", &email_verification_url(&token)),
+                ("hours", &ttl.num_hours().to_string()),
+            ],
+        ))
+        .await
+}
+
+/// Verifies the email of an account with the token of a verification link.
+///
+/// # Example query
+/// `/email-verification?token=v2.local.xxxx`
+pub async fn verify_email_handler(
+    params: HashMap<String, String>,
+    store: Store,
+) -> Result<impl Reply, Rejection> {
+    let token = params
+        .get("token")
+        .ok_or(handle_errors::Error::MissingParameters)?;
+    let verification = decode_token(token.to_string())
+        .ok()
+        .and_then(|value| serde_json::from_value::<EmailVerification>(value).ok())
+        .ok_or(handle_errors::Error::InvalidVerificationToken)?;
+
+    verification.validate(Utc::now())?;
+
+    match store
+        .verify_email(verification.account_id.0, verification.email)
+        .await
+    {
+        Ok(true) => Ok(reply::with_status("Email verified!", StatusCode::OK)),
+        Ok(false) => Err(reject::custom(
+            handle_errors::Error::InvalidVerificationToken,
+        )),
+        Err(err) => Err(reject::custom(err)),
+    }
+}
+
+/// Sends a new verification link to the email of the account of the session.
+pub async fn resend_verification_handler(
+    session: Session,
+    store: Store,
+    mailer: SharedMailer,
+) -> Result<impl Reply, Rejection> {
+    let account = store.get_account_details(session.account_id.0).await?;
+
+    if account.email_verified_on.is_some() {
+        return Ok(reply::with_status(
+            "Email already verified!",
+            StatusCode::OK,
+        ));
+    }
+
+    match send_verification_email(&account.id, &account.email, &mailer).await {
+        Ok(_) => Ok(reply::with_status(
+            "Verification email sent!",
+            StatusCode::OK,
+        )),
+        Err(err) => {
+            event!(
+                Level::ERROR,
+                "Could not send the verification email: {}",
+                err
+            );
+
+            Err(reject::custom(err))
+        }
+    }
+}
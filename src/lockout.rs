@@ -0,0 +1,264 @@
+use chrono::{DateTime, Duration, Utc};
+use parking_lot::Mutex;
+use std::{
+    collections::HashMap,
+    env,
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+};
+use tracing::{event, Level};
+
+const DEFAULT_MAX_FAILED_ATTEMPTS: u32 = 5;
+const DEFAULT_MAX_FAILED_ATTEMPTS_PER_IP: u32 = 20;
+const DEFAULT_LOCKOUT_SECS: i64 = 30;
+const DEFAULT_MAX_LOCKOUT_SECS: i64 = 3600;
+/// Number of tracked IPs above which the IPs without recent failures are forgotten
+const MAX_TRACKED_IPS: usize = 10_000;
+
+#[derive(Debug, Clone)]
+pub struct LockoutConfig {
+    /// Failed logins of an account before it is locked
+    pub max_failed_attempts: u32,
+    /// Failed logins from an IP, to any account, before the IP is locked
+    pub max_failed_attempts_per_ip: u32,
+    /// Lockout after reaching the maximum failed attempts, it doubles on every further failure
+    pub lockout: Duration,
+    pub max_lockout: Duration,
+    /// Read the client IP from the `X-Forwarded-For` header appended by a reverse proxy
+    pub trust_proxy: bool,
+}
+
+impl LockoutConfig {
+    /// Reads the configuration from the env variables:
+    ///
+    /// - `LOGIN_MAX_FAILED_ATTEMPTS`, defaults to 5
+    /// - `LOGIN_MAX_FAILED_ATTEMPTS_PER_IP`, defaults to 20
+    /// - `LOGIN_LOCKOUT_SECS`, defaults to 30
+    /// - `LOGIN_MAX_LOCKOUT_SECS`, defaults to 3600
+    /// - `LOGIN_TRUST_PROXY`, defaults to false
+    pub fn from_env() -> Self {
+        let max_failed_attempts = env::var("LOGIN_MAX_FAILED_ATTEMPTS")
+            .ok()
+            .and_then(|attempts| attempts.parse::<u32>().ok())
+            .unwrap_or(DEFAULT_MAX_FAILED_ATTEMPTS);
+        let max_failed_attempts_per_ip = env::var("LOGIN_MAX_FAILED_ATTEMPTS_PER_IP")
+            .ok()
+            .and_then(|attempts| attempts.parse::<u32>().ok())
+            .unwrap_or(DEFAULT_MAX_FAILED_ATTEMPTS_PER_IP);
+        let lockout_secs = env::var("LOGIN_LOCKOUT_SECS")
+            .ok()
+            .and_then(|secs| secs.parse::<i64>().ok())
+            .unwrap_or(DEFAULT_LOCKOUT_SECS);
+        let max_lockout_secs = env::var("LOGIN_MAX_LOCKOUT_SECS")
+            .ok()
+            .and_then(|secs| secs.parse::<i64>().ok())
+            .unwrap_or(DEFAULT_MAX_LOCKOUT_SECS);
+        let trust_proxy = env::var("LOGIN_TRUST_PROXY")
+            .ok()
+            .and_then(|trust| trust.parse::<bool>().ok())
+            .unwrap_or(false);
+
+        LockoutConfig {
+            max_failed_attempts,
+            max_failed_attempts_per_ip,
+            lockout: Duration::seconds(lockout_secs),
+            max_lockout: Duration::seconds(max_lockout_secs),
+            trust_proxy,
+        }
+    }
+
+    /// Returns how long to lock an account or an IP after `failed_attempts` consecutive failures.
+    ///
+    /// Nothing is locked until `max_attempts` is reached, then the lockout doubles on every failure
+    /// up to `max_lockout`.
+    fn lockout_after(&self, failed_attempts: u32, max_attempts: u32) -> Option<Duration> {
+        if failed_attempts < max_attempts {
+            return None;
+        }
+
+        let max_lockout_secs = self.max_lockout.num_seconds();
+        let lockout_secs = 2i64
+            .checked_pow(failed_attempts - max_attempts)
+            .and_then(|factor| self.lockout.num_seconds().checked_mul(factor))
+            .map_or(max_lockout_secs, |secs| secs.min(max_lockout_secs));
+
+        Some(Duration::seconds(lockout_secs))
+    }
+}
+
+#[derive(Debug)]
+struct IpAttempts {
+    failed: u32,
+    last_failure: DateTime<Utc>,
+    locked_until: Option<DateTime<Utc>>,
+}
+
+/// Tracks the failed logins of the accounts and of the client IPs.
+///
+/// The failed logins of an account are stored in the database (see `Store::record_failed_login`),
+/// so they are shared by every instance of the service. The failed logins of an IP are kept in
+/// memory and forgotten after `max_lockout` without failures. Successful logins do not reset them,
+/// otherwise logging in to an own account between guesses would avoid the IP lockout.
+pub struct LoginThrottle {
+    config: LockoutConfig,
+    ips: Mutex<HashMap<IpAddr, IpAttempts>>,
+}
+
+impl LoginThrottle {
+    pub fn new(config: LockoutConfig) -> Self {
+        LoginThrottle {
+            config,
+            ips: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Lockout of an account after `failed_attempts` consecutive failed logins.
+    pub fn account_lockout(&self, failed_attempts: u32) -> Option<Duration> {
+        self.config
+            .lockout_after(failed_attempts, self.config.max_failed_attempts)
+    }
+
+    /// Returns the client IP of a request, read from the `X-Forwarded-For` header when the service
+    /// is behind a trusted proxy.
+    ///
+    /// Only the last entry of the header is used: it is the one appended by the proxy, the others
+    /// come from the client and can be forged.
+    pub fn client_ip(
+        &self,
+        forwarded_for: Option<&str>,
+        remote: Option<SocketAddr>,
+    ) -> Option<IpAddr> {
+        let forwarded = forwarded_for
+            .filter(|_| self.config.trust_proxy)
+            .and_then(|header| header.rsplit(',').next())
+            .and_then(|ip| ip.trim().parse::<IpAddr>().ok());
+
+        forwarded.or(remote.map(|addr| addr.ip()))
+    }
+
+    /// Returns `Error::TooManyLoginAttempts` with the seconds left when an IP is locked.
+    pub fn check_ip(&self, ip: IpAddr, now: DateTime<Utc>) -> Result<(), handle_errors::Error> {
+        match self
+            .ips
+            .lock()
+            .get(&ip)
+            .and_then(|attempts| attempts.locked_until)
+        {
+            Some(until) if until > now => Err(handle_errors::Error::TooManyLoginAttempts(
+                (until - now).num_seconds().max(1),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn record_ip_failure(&self, ip: IpAddr, now: DateTime<Utc>) {
+        let mut ips = self.ips.lock();
+
+        if ips.len() >= MAX_TRACKED_IPS {
+            ips.retain(|_, attempts| now - attempts.last_failure < self.config.max_lockout);
+        }
+
+        let attempts = ips.entry(ip).or_insert(IpAttempts {
+            failed: 0,
+            last_failure: now,
+            locked_until: None,
+        });
+
+        // Failures are forgotten once an IP has not failed for the longest lockout
+        if now - attempts.last_failure >= self.config.max_lockout {
+            attempts.failed = 0;
+        }
+
+        attempts.failed += 1;
+        attempts.last_failure = now;
+
+        if let Some(lockout) = self
+            .config
+            .lockout_after(attempts.failed, self.config.max_failed_attempts_per_ip)
+        {
+            event!(
+                Level::WARN,
+                %ip,
+                failed_attempts = attempts.failed,
+                "Too many failed logins, the IP is locked for {} seconds",
+                lockout.num_seconds()
+            );
+
+            attempts.locked_until = Some(now + lockout);
+        }
+    }
+}
+
+pub type Throttle = Arc<LoginThrottle>;
+
+#[cfg(test)]
+mod lockout_tests {
+    use chrono::{Duration, Utc};
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+    use super::{LockoutConfig, LoginThrottle};
+
+    fn throttle(trust_proxy: bool) -> LoginThrottle {
+        LoginThrottle::new(LockoutConfig {
+            max_failed_attempts: 3,
+            max_failed_attempts_per_ip: 2,
+            lockout: Duration::seconds(30),
+            max_lockout: Duration::seconds(100),
+            trust_proxy,
+        })
+    }
+
+    #[test]
+    fn exponential_account_lockout() {
+        let throttle = throttle(false);
+
+        assert_eq!(throttle.account_lockout(2), None);
+        assert_eq!(throttle.account_lockout(3), Some(Duration::seconds(30)));
+        assert_eq!(throttle.account_lockout(4), Some(Duration::seconds(60)));
+        assert_eq!(throttle.account_lockout(5), Some(Duration::seconds(100)));
+        assert_eq!(throttle.account_lockout(100), Some(Duration::seconds(100)));
+    }
+
+    #[test]
+    fn lock_ip_after_failures() {
+        let throttle = throttle(false);
+        let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let now = Utc::now();
+
+        throttle.record_ip_failure(ip, now);
+        assert!(throttle.check_ip(ip, now).is_ok());
+
+        throttle.record_ip_failure(ip, now);
+        assert_eq!(
+            format!("{}", throttle.check_ip(ip, now).unwrap_err()),
+            format!("{}", handle_errors::Error::TooManyLoginAttempts(30))
+        );
+        assert!(throttle.check_ip(ip, now + Duration::seconds(30)).is_ok());
+
+        throttle.record_ip_failure(ip, now + Duration::seconds(100));
+        assert!(throttle.check_ip(ip, now + Duration::seconds(100)).is_ok());
+    }
+
+    #[test]
+    fn client_ip_behind_proxy() {
+        let remote = Some(SocketAddr::from(([10, 0, 0, 1], 4000)));
+        let forwarded = Some("198.51.100.1, 203.0.113.7");
+
+        assert_eq!(
+            throttle(false).client_ip(forwarded, remote),
+            Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)))
+        );
+        assert_eq!(
+            throttle(true).client_ip(forwarded, remote),
+            Some(IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7)))
+        );
+        assert_eq!(
+            throttle(true).client_ip(Some("203.0.113.7"), remote),
+            Some(IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7)))
+        );
+        assert_eq!(
+            throttle(true).client_ip(Some("unknown"), remote),
+            Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)))
+        );
+    }
+}
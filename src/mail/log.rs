@@ -0,0 +1,23 @@
+use async_trait::async_trait;
+use tracing::{event, Level};
+
+use super::{Email, Mailer};
+
+/// Mailer for local development which writes the emails to the logs instead of sending them.
+#[derive(Debug, Clone, Default)]
+pub struct LogMailer;
+
+#[async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, email: Email) -> Result<(), handle_errors::Error> {
+        event!(
+            Level::INFO,
+            to = %email.to,
+            subject = %email.subject,
+            "Email:\n{}",
+            email.body
+        );
+
+        Ok(())
+    }
+}
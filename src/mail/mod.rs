@@ -0,0 +1,62 @@
+use async_trait::async_trait;
+use lettre::{message::Mailbox, Message};
+use std::{env, sync::Arc};
+
+pub mod log;
+pub mod queue;
+pub mod smtp;
+pub mod spool;
+pub mod templates;
+
+pub use self::log::LogMailer;
+pub use queue::{MailQueue, MailQueueConfig};
+pub use smtp::{SmtpConfig, SmtpMailer};
+pub use spool::SpoolMailer;
+pub use templates::Template;
+
+const DEFAULT_MAIL_FROM: &str = "Questions & Answers <no-reply@localhost>";
+
+/// A plain text email.
+#[derive(Debug, Clone)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Service that delivers the emails sent to the users, e.g. the password reset links.
+///
+/// Handlers use the `MailQueue`, which sends the emails in the background with one of the
+/// backends (`SmtpMailer`, `SpoolMailer` or `LogMailer`) and retries the failed ones. Backends
+/// fail with `Error::EmailRejected` when sending the email again cannot succeed.
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, email: Email) -> Result<(), handle_errors::Error>;
+}
+
+pub type SharedMailer = Arc<dyn Mailer>;
+
+/// Sender of the emails, read from `MAIL_FROM` (defaults to
+/// `Questions & Answers <no-reply@localhost>`).
+pub fn mail_from_env() -> Result<Mailbox, handle_errors::Error> {
+    env::var("MAIL_FROM")
+        .unwrap_or(String::from(DEFAULT_MAIL_FROM))
+        .parse::<Mailbox>()
+        .map_err(|err| handle_errors::Error::MailError(err.to_string()))
+}
+
+/// Builds the MIME message of an email. An email that cannot be built fails with
+/// `Error::EmailRejected`, sending it again would fail the same way.
+fn message(from: &Mailbox, email: Email) -> Result<Message, handle_errors::Error> {
+    let to = email
+        .to
+        .parse::<Mailbox>()
+        .map_err(|err| handle_errors::Error::EmailRejected(err.to_string()))?;
+
+    Message::builder()
+        .from(from.clone())
+        .to(to)
+        .subject(email.subject)
+        .body(email.body)
+        .map_err(|err| handle_errors::Error::EmailRejected(err.to_string()))
+}
@@ -0,0 +1,223 @@
+use async_trait::async_trait;
+use std::{env, time::Duration};
+use tokio::sync::mpsc;
+use tracing::{event, Level};
+
+use super::{Email, Mailer, SharedMailer};
+
+const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+const DEFAULT_RETRY_DELAY_SECS: u64 = 5;
+
+#[derive(Debug, Clone)]
+pub struct MailQueueConfig {
+    /// Number of times an email is sent before giving up
+    pub max_attempts: u32,
+    /// Delay before the first retry, it doubles on every retry
+    pub retry_delay: Duration,
+}
+
+impl MailQueueConfig {
+    /// Reads the configuration from the env variables:
+    ///
+    /// - `MAIL_MAX_ATTEMPTS`, defaults to 5
+    /// - `MAIL_RETRY_DELAY_SECS`, defaults to 5
+    pub fn from_env() -> Self {
+        let max_attempts = env::var("MAIL_MAX_ATTEMPTS")
+            .ok()
+            .and_then(|attempts| attempts.parse::<u32>().ok())
+            .unwrap_or(DEFAULT_MAX_ATTEMPTS);
+        let retry_delay_secs = env::var("MAIL_RETRY_DELAY_SECS")
+            .ok()
+            .and_then(|secs| secs.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_RETRY_DELAY_SECS);
+
+        MailQueueConfig {
+            max_attempts,
+            retry_delay: Duration::from_secs(retry_delay_secs),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Delivery {
+    email: Email,
+    attempt: u32,
+}
+
+/// Mailer which sends the emails in the background, so handlers do not wait for the mail server.
+///
+/// `send` only queues the email. A background task sends the queued emails one by one with the
+/// inner mailer and queues the failed ones again after `retry_delay`, doubling the delay on every
+/// attempt, until they have been tried `max_attempts` times. The emails rejected with
+/// `Error::EmailRejected` are dropped without retrying them.
+pub struct MailQueue {
+    sender: mpsc::UnboundedSender<Delivery>,
+}
+
+impl MailQueue {
+    /// Creates the queue and spawns its background task.
+    pub fn spawn(inner: SharedMailer, config: MailQueueConfig) -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        tokio::spawn(deliver(inner, config, sender.clone(), receiver));
+
+        MailQueue { sender }
+    }
+}
+
+#[async_trait]
+impl Mailer for MailQueue {
+    async fn send(&self, email: Email) -> Result<(), handle_errors::Error> {
+        self.sender
+            .send(Delivery { email, attempt: 1 })
+            .map_err(|_| handle_errors::Error::MailError(String::from("The mail queue is closed")))
+    }
+}
+
+async fn deliver(
+    inner: SharedMailer,
+    config: MailQueueConfig,
+    sender: mpsc::UnboundedSender<Delivery>,
+    mut receiver: mpsc::UnboundedReceiver<Delivery>,
+) {
+    while let Some(delivery) = receiver.recv().await {
+        let err = match inner.send(delivery.email.clone()).await {
+            Ok(_) => continue,
+            Err(err) => err,
+        };
+
+        if let handle_errors::Error::EmailRejected(_) = err {
+            event!(
+                Level::ERROR,
+                to = %delivery.email.to,
+                attempts = delivery.attempt,
+                "Dropping an email: {}",
+                err
+            );
+
+            continue;
+        }
+
+        if delivery.attempt >= config.max_attempts {
+            event!(
+                Level::ERROR,
+                to = %delivery.email.to,
+                attempts = delivery.attempt,
+                "Giving up sending an email: {}",
+                err
+            );
+
+            continue;
+        }
+
+        let delay = config.retry_delay * 2u32.saturating_pow(delivery.attempt - 1);
+        let sender = sender.clone();
+
+        event!(
+            Level::WARN,
+            to = %delivery.email.to,
+            attempt = delivery.attempt,
+            "Could not send an email, retrying in {:?}: {}",
+            delay,
+            err
+        );
+
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+
+            let _ = sender.send(Delivery {
+                email: delivery.email,
+                attempt: delivery.attempt + 1,
+            });
+        });
+    }
+}
+
+#[cfg(test)]
+mod queue_tests {
+    use async_trait::async_trait;
+    use std::{
+        sync::atomic::{AtomicU32, Ordering},
+        sync::Arc,
+        time::Duration,
+    };
+    use tokio::sync::mpsc;
+
+    use super::{Email, MailQueue, MailQueueConfig, Mailer};
+
+    /// Mailer that fails the first `failures` emails and reports whether every attempt succeeded
+    struct FlakyMailer {
+        failures: u32,
+        rejected: bool,
+        attempts: AtomicU32,
+        results: mpsc::UnboundedSender<bool>,
+    }
+
+    #[async_trait]
+    impl Mailer for FlakyMailer {
+        async fn send(&self, _email: Email) -> Result<(), handle_errors::Error> {
+            let failed = self.attempts.fetch_add(1, Ordering::SeqCst) < self.failures;
+            let _ = self.results.send(!failed);
+
+            match (failed, self.rejected) {
+                (false, _) => Ok(()),
+                (true, false) => Err(handle_errors::Error::MailError(String::from("unavailable"))),
+                (true, true) => Err(handle_errors::Error::EmailRejected(String::from("invalid"))),
+            }
+        }
+    }
+
+    fn email() -> Email {
+        Email {
+            to: String::from("test@email.com"),
+            subject: String::from("Subject"),
+            body: String::from("Body"),
+        }
+    }
+
+    /// Queues an email and returns the results of its attempts, waiting for `expected` of them.
+    async fn send(failures: u32, rejected: bool, max_attempts: u32, expected: usize) -> Vec<bool> {
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let queue = MailQueue::spawn(
+            Arc::new(FlakyMailer {
+                failures,
+                rejected,
+                attempts: AtomicU32::new(0),
+                results: sender,
+            }),
+            MailQueueConfig {
+                max_attempts,
+                retry_delay: Duration::from_millis(1),
+            },
+        );
+
+        queue.send(email()).await.unwrap();
+
+        let mut results = Vec::new();
+
+        while results.len() < expected {
+            results.push(receiver.recv().await.unwrap());
+        }
+
+        // A retry would come after a few milliseconds at most
+        let extra = tokio::time::timeout(Duration::from_millis(50), receiver.recv()).await;
+        assert!(extra.is_err(), "unexpected attempt: {:?}", extra);
+
+        results
+    }
+
+    #[tokio::test]
+    async fn retry_failed_emails() {
+        assert_eq!(send(2, false, 5, 3).await, vec![false, false, true]);
+    }
+
+    #[tokio::test]
+    async fn give_up_after_max_attempts() {
+        assert_eq!(send(10, false, 3, 3).await, vec![false, false, false]);
+    }
+
+    #[tokio::test]
+    async fn drop_rejected_emails() {
+        assert_eq!(send(10, true, 5, 1).await, vec![false]);
+    }
+}
@@ -0,0 +1,109 @@
+use async_trait::async_trait;
+use lettre::{
+    message::Mailbox, transport::smtp::authentication::Credentials, AsyncSmtpTransport,
+    AsyncTransport, Tokio1Executor,
+};
+use std::env;
+
+use super::{mail_from_env, message, Email, Mailer};
+
+const DEFAULT_SMTP_PORT: u16 = 587;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmtpTls {
+    /// Plain connection upgraded with `STARTTLS`, usually on port 587
+    StartTls,
+    /// TLS connection from the start, usually on port 465
+    Tls,
+    /// Unencrypted connection, only meant for local mail servers
+    None,
+}
+
+#[derive(Debug, Clone)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub tls: SmtpTls,
+    pub credentials: Option<(String, String)>,
+    pub from: Mailbox,
+}
+
+impl SmtpConfig {
+    /// Reads the configuration from the env variables:
+    ///
+    /// - `SMTP_HOST` (required)
+    /// - `SMTP_PORT`, defaults to 587
+    /// - `SMTP_TLS`: `starttls` (default), `tls` or `none`
+    /// - `SMTP_USERNAME` and `SMTP_PASSWORD`, without them the mails are sent without authentication
+    /// - `MAIL_FROM`, see `mail_from_env`
+    pub fn from_env() -> Result<Self, handle_errors::Error> {
+        let host = env::var("SMTP_HOST").map_err(|_| handle_errors::Error::EnvVariableError)?;
+        let port = env::var("SMTP_PORT")
+            .ok()
+            .and_then(|port| port.parse::<u16>().ok())
+            .unwrap_or(DEFAULT_SMTP_PORT);
+        let tls = match env::var("SMTP_TLS").as_deref() {
+            Ok("tls") => SmtpTls::Tls,
+            Ok("none") => SmtpTls::None,
+            _ => SmtpTls::StartTls,
+        };
+        let credentials = env::var("SMTP_USERNAME")
+            .ok()
+            .zip(env::var("SMTP_PASSWORD").ok());
+
+        Ok(SmtpConfig {
+            host,
+            port,
+            tls,
+            credentials,
+            from: mail_from_env()?,
+        })
+    }
+}
+
+/// Mailer which sends the emails to an SMTP server.
+///
+/// The transport keeps a pool of connections, so emails sent close together reuse the same
+/// connection to the server.
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    pub fn new(config: SmtpConfig) -> Result<Self, handle_errors::Error> {
+        let builder = match config.tls {
+            SmtpTls::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host),
+            SmtpTls::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host),
+            SmtpTls::None => Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(
+                &config.host,
+            )),
+        }
+        .map_err(|err| handle_errors::Error::MailError(err.to_string()))?;
+        let builder = match config.credentials {
+            Some((username, password)) => builder.credentials(Credentials::new(username, password)),
+            None => builder,
+        };
+
+        Ok(SmtpMailer {
+            transport: builder.port(config.port).build(),
+            from: config.from,
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, email: Email) -> Result<(), handle_errors::Error> {
+        let message = message(&self.from, email)?;
+
+        self.transport
+            .send(message)
+            .await
+            .map(|_| ())
+            .map_err(|err| match err.is_permanent() {
+                true => handle_errors::Error::EmailRejected(err.to_string()),
+                false => handle_errors::Error::MailError(err.to_string()),
+            })
+    }
+}
@@ -0,0 +1,80 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use lettre::message::Mailbox;
+use std::path::PathBuf;
+use tokio::fs;
+use uuid::Uuid;
+
+use super::{message, Email, Mailer};
+
+/// Mailer for development and integration tests which writes every email to a directory instead of
+/// sending it.
+///
+/// Each email is stored as a `.eml` file named after the time it was sent, so the files sort in
+/// the order the emails were sent and they can be opened with any mail client.
+#[derive(Debug, Clone)]
+pub struct SpoolMailer {
+    directory: PathBuf,
+    from: Mailbox,
+}
+
+impl SpoolMailer {
+    pub fn new<P: Into<PathBuf>>(directory: P, from: Mailbox) -> Self {
+        SpoolMailer {
+            directory: directory.into(),
+            from,
+        }
+    }
+}
+
+#[async_trait]
+impl Mailer for SpoolMailer {
+    async fn send(&self, email: Email) -> Result<(), handle_errors::Error> {
+        let message = message(&self.from, email)?;
+        let path = self.directory.join(format!(
+            "{}-{}.eml",
+            Utc::now().format("%Y%m%dT%H%M%S%.6fZ"),
+            Uuid::new_v4()
+        ));
+
+        fs::create_dir_all(&self.directory)
+            .await
+            .map_err(|err| handle_errors::Error::MailError(err.to_string()))?;
+        fs::write(&path, message.formatted())
+            .await
+            .map_err(|err| handle_errors::Error::MailError(err.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod spool_tests {
+    use std::env;
+    use uuid::Uuid;
+
+    use super::{Email, Mailer, SpoolMailer};
+
+    #[tokio::test]
+    async fn write_emails_to_the_directory() {
+        let directory = env::temp_dir().join(format!("mail-spool-{}", Uuid::new_v4()));
+        let mailer = SpoolMailer::new(&directory, "no-reply@localhost".parse().unwrap());
+
+        mailer
+            .send(Email {
+                to: String::from("test@email.com"),
+                subject: String::from("Verify your email"),
+                body: String::from("Hello!"),
+            })
+            .await
+            .unwrap();
+
+        let files = std::fs::read_dir(&directory).unwrap().collect::<Vec<_>>();
+        let content = std::fs::read_to_string(files[0].as_ref().unwrap().path()).unwrap();
+
+        assert_eq!(files.len(), 1);
+        assert!(content.contains("To: test@email.com"));
+        assert!(content.contains("Subject: Verify your email"));
+        assert!(content.contains("Hello!"));
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,81 @@
+use super::Email;
+
+/// Emails sent by the service. Their bodies are read from `templates/mail` at compile time and
+/// their `{{name}}` placeholders are replaced when they are rendered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Template {
+    /// Placeholders: `url` and `hours`
+    EmailVerification,
+    /// Placeholders: `url` and `minutes`
+    PasswordReset,
+}
+
+impl Template {
+    fn subject(&self) -> &'static str {
+        match self {
+            Template::EmailVerification => "Verify your email",
+            Template::PasswordReset => "Reset your password",
+        }
+    }
+
+    fn body(&self) -> &'static str {
+        match self {
+            Template::EmailVerification => {
+                include_str!("../../templates/mail/email_verification.txt")
+            }
+            Template::PasswordReset => include_str!("../../templates/mail/password_reset.txt"),
+        }
+    }
+
+    /// Returns the email of the template for `to`, replacing its placeholders by `values`.
+    pub fn render(&self, to: &str, values: &[(&str, &str)]) -> Email {
+        Email {
+            to: to.to_string(),
+            subject: self.subject().to_string(),
+            body: render(self.body(), values),
+        }
+    }
+}
+
+/// Replaces the `{{name}}` placeholders of a template. Placeholders without a value are kept.
+fn render(template: &str, values: &[(&str, &str)]) -> String {
+    values
+        .iter()
+        .fold(template.to_string(), |content, (name, value)| {
+            content.replace(&format!("{{{{{}}}}}", name), value)
+        })
+}
+
+#[cfg(test)]
+mod templates_tests {
+    use super::{render, Template};
+
+    #[test]
+    fn replace_placeholders() {
+        assert_eq!(
+            render(
+                "{{url}} expires in {{hours}} hours, {{url}}",
+                &[("url", "http://a"), ("hours", "24")]
+            ),
+            "http://a expires in 24 hours, http://a"
+        );
+        assert_eq!(render("Hi {{name}}", &[]), "Hi {{name}}");
+    }
+
+    #[test]
+    fn render_template() {
+        let email = Template::PasswordReset.render(
+            "test@email.com",
+            &[
+                ("url", "http://localhost/reset?token=abc"),
+                ("minutes", "60"),
+            ],
+        );
+
+        assert_eq!(email.to, "test@email.com");
+        assert_eq!(email.subject, "Reset your password");
+        assert!(email.body.contains("http://localhost/reset?token=abc"));
+        assert!(email.body.contains("expires in 60 minutes"));
+        assert!(!email.body.contains("{{"));
+    }
+}
@@ -1,13 +1,19 @@
+// The routes are chained with `or`, which nests one filter type per route
+#![recursion_limit = "256"]
+
 mod crypt;
 mod handlers;
+mod lockout;
+mod mail;
+mod moderation;
 mod profanity;
 mod store;
 mod types;
 
-use dotenv;
 use handle_errors::error_handler;
-use std::env;
+use std::{env, sync::Arc};
 use tracing_subscriber::fmt::format::FmtSpan;
+use types::account::Role;
 use warp::{http::Method, path, Filter};
 
 #[tokio::main]
@@ -27,10 +33,97 @@ async fn main() {
         .init();
 
     let store = store::Store::new(&database_url).await;
-    let store_filter = warp::any().map(move || store.clone());
+    let store_filter = {
+        let store = store.clone();
+
+        warp::any().map(move || store.clone())
+    };
+    let profanity: profanity::Profanity = match env::var("PROFANITY_FILTER")
+        .unwrap_or(String::from("apilayer"))
+        .as_str()
+    {
+        "apilayer" => {
+            let config = profanity::ApiLayerConfig::from_env()
+                .expect("BAD_WORDS_API_KEY env variable is missing.");
+
+            Arc::new(
+                profanity::ApiLayerFilter::new(config)
+                    .expect("Could not create the profanity API client."),
+            )
+        }
+        "word_list" => {
+            let word_list_path = env::var("PROFANITY_WORD_LIST")
+                .expect("PROFANITY_WORD_LIST env variable is missing.");
+
+            Arc::new(
+                profanity::WordListFilter::from_file(&word_list_path)
+                    .expect("Could not read the profanity word list."),
+            )
+        }
+        other => panic!(
+            "Unknown PROFANITY_FILTER {}, expected apilayer or word_list.",
+            other
+        ),
+    };
+    let circuit_breaker = Arc::new(profanity::CircuitBreaker::new(
+        profanity,
+        profanity::CircuitBreakerConfig::from_env(),
+    ));
+    // The cache goes in front of the circuit breaker, so cached results are returned even when the
+    // circuit is open
+    let profanity_cache = Arc::new(profanity::ProfanityCache::new(
+        circuit_breaker.clone(),
+        profanity::CacheConfig::from_env(),
+    ));
+    let moderation: moderation::Moderation = Arc::new(moderation::Moderator::new(
+        profanity_cache.clone(),
+        moderation::FailurePolicy::from_env(),
+        moderation::ModerationMode::from_env(),
+        moderation::bad_words_threshold_from_env(),
+    ));
+
+    moderation::spawn_recheck_worker(store.clone(), moderation.clone());
+
+    let mailer: mail::SharedMailer =
+        match env::var("MAILER").unwrap_or(String::from("log")).as_str() {
+            "log" => Arc::new(mail::LogMailer),
+            "smtp" => {
+                let config = mail::SmtpConfig::from_env()
+                    .expect("SMTP_HOST env variable is missing or MAIL_FROM is not valid.");
+
+                Arc::new(mail::SmtpMailer::new(config).expect("Could not create the SMTP client."))
+            }
+            "spool" => {
+                let directory = env::var("MAIL_SPOOL_DIR").unwrap_or(String::from("mail_spool"));
+                let from = mail::mail_from_env().expect("MAIL_FROM is not a valid mailbox.");
+
+                Arc::new(mail::SpoolMailer::new(directory, from))
+            }
+            other => panic!("Unknown MAILER {}, expected log, smtp or spool.", other),
+        };
+    // Handlers only queue the emails, they are sent in the background
+    let mailer: mail::SharedMailer = Arc::new(mail::MailQueue::spawn(
+        mailer,
+        mail::MailQueueConfig::from_env(),
+    ));
+
+    let throttle: lockout::Throttle = Arc::new(lockout::LoginThrottle::new(
+        lockout::LockoutConfig::from_env(),
+    ));
+
+    let mailer_filter = warp::any().map(move || mailer.clone());
+    let throttle_filter = {
+        let throttle = throttle.clone();
+
+        warp::any().map(move || throttle.clone())
+    };
+    let circuit_breaker_filter = warp::any().map(move || circuit_breaker.clone());
+    let profanity_cache_filter = warp::any().map(move || profanity_cache.clone());
+    let moderation_filter = warp::any().map(move || moderation.clone());
     let cors = warp::cors()
         .allow_any_origin()
-        .allow_header("Content-Type")
+        .allow_headers(vec!["Content-Type", "Authorization"])
+        .expose_header("WWW-Authenticate")
         .allow_methods(&[Method::GET, Method::POST, Method::PUT, Method::DELETE]);
     // Questions Handlers
     let get_questions = warp::get()
@@ -42,23 +135,25 @@ async fn main() {
     let add_question = warp::post()
         .and(path("questions"))
         .and(path::end())
-        .and(handlers::auth::auth())
+        .and(handlers::auth::verified_auth(store.clone()))
         .and(warp::body::json())
         .and(store_filter.clone())
+        .and(moderation_filter.clone())
         .and_then(handlers::questions::add_question_handler);
     let update_question = warp::put()
         .and(path("questions"))
         .and(path::param::<i32>())
         .and(path::end())
-        .and(handlers::auth::auth())
+        .and(handlers::auth::verified_auth(store.clone()))
         .and(warp::body::json())
         .and(store_filter.clone())
+        .and(moderation_filter.clone())
         .and_then(handlers::questions::update_question_handler);
     let delete_question = warp::delete()
         .and(path("questions"))
         .and(path::param::<i32>())
         .and(path::end())
-        .and(handlers::auth::auth())
+        .and(handlers::auth::auth(store.clone()))
         .and(store_filter.clone())
         .and_then(handlers::questions::delete_question_handler);
     let get_question = warp::get()
@@ -67,26 +162,272 @@ async fn main() {
         .and(path::end())
         .and(store_filter.clone())
         .and_then(handlers::questions::get_question_handler);
+    let accept_answer = warp::post()
+        .and(path("questions"))
+        .and(path::param::<i32>())
+        .and(path("accept"))
+        .and(path::param::<i32>())
+        .and(path::end())
+        .and(handlers::auth::auth(store.clone()))
+        .and(store_filter.clone())
+        .and_then(handlers::questions::accept_answer_handler);
+    let unaccept_answer = warp::delete()
+        .and(path("questions"))
+        .and(path::param::<i32>())
+        .and(path("accept"))
+        .and(path::end())
+        .and(handlers::auth::auth(store.clone()))
+        .and(store_filter.clone())
+        .and_then(handlers::questions::unaccept_answer_handler);
+    let vote_question = warp::post()
+        .and(path("questions"))
+        .and(path::param::<i32>())
+        .and(path("vote"))
+        .and(path::end())
+        .and(handlers::auth::auth(store.clone()))
+        .and(warp::body::json())
+        .and(store_filter.clone())
+        .and_then(handlers::questions::vote_question_handler);
+    // Tags Handlers
+    let get_tags = warp::get()
+        .and(path("tags"))
+        .and(path::end())
+        .and(warp::query())
+        .and(store_filter.clone())
+        .and_then(handlers::tags::get_tags_handler);
+    let update_tag = warp::put()
+        .and(path("tags"))
+        .and(path::param::<String>())
+        .and(path::end())
+        .and(handlers::auth::require_role(store.clone(), Role::Moderator))
+        .and(warp::body::json())
+        .and(store_filter.clone())
+        .and_then(handlers::tags::update_tag_handler);
     // Answers Handlers
+    let get_answers = warp::get()
+        .and(path("questions"))
+        .and(path::param::<i32>())
+        .and(path("answers"))
+        .and(path::end())
+        .and(warp::query())
+        .and(store_filter.clone())
+        .and_then(handlers::answers::get_answers_handler);
     let add_answer = warp::post()
         .and(path("answers"))
         .and(path::end())
-        .and(handlers::auth::auth())
+        .and(handlers::auth::verified_auth(store.clone()))
         .and(warp::body::json())
         .and(store_filter.clone())
+        .and(moderation_filter.clone())
         .and_then(handlers::answers::add_answer_handler);
+    let update_answer = warp::put()
+        .and(path("answers"))
+        .and(path::param::<i32>())
+        .and(path::end())
+        .and(handlers::auth::verified_auth(store.clone()))
+        .and(warp::body::json())
+        .and(store_filter.clone())
+        .and(moderation_filter.clone())
+        .and_then(handlers::answers::update_answer_handler);
+    let delete_answer = warp::delete()
+        .and(path("answers"))
+        .and(path::param::<i32>())
+        .and(path::end())
+        .and(handlers::auth::auth(store.clone()))
+        .and(store_filter.clone())
+        .and_then(handlers::answers::delete_answer_handler);
+    let vote_answer = warp::post()
+        .and(path("answers"))
+        .and(path::param::<i32>())
+        .and(path("vote"))
+        .and(path::end())
+        .and(handlers::auth::auth(store.clone()))
+        .and(warp::body::json())
+        .and(store_filter.clone())
+        .and_then(handlers::answers::vote_answer_handler);
+    // Flags Handlers
+    let flag_question = warp::post()
+        .and(path("questions"))
+        .and(path::param::<i32>())
+        .and(path("flag"))
+        .and(path::end())
+        .and(handlers::auth::auth(store.clone()))
+        .and(warp::body::json())
+        .and(store_filter.clone())
+        .and_then(handlers::flags::flag_question_handler);
+    let flag_answer = warp::post()
+        .and(path("answers"))
+        .and(path::param::<i32>())
+        .and(path("flag"))
+        .and(path::end())
+        .and(handlers::auth::auth(store.clone()))
+        .and(warp::body::json())
+        .and(store_filter.clone())
+        .and_then(handlers::flags::flag_answer_handler);
+    let get_flags = warp::get()
+        .and(path("moderation"))
+        .and(path("flags"))
+        .and(path::end())
+        .and(warp::query())
+        .and(handlers::auth::require_role(store.clone(), Role::Moderator))
+        .and(store_filter.clone())
+        .and_then(handlers::flags::get_flags_handler);
+    let review_flag = warp::put()
+        .and(path("moderation"))
+        .and(path("flags"))
+        .and(path::param::<i32>())
+        .and(path::end())
+        .and(handlers::auth::require_role(store.clone(), Role::Moderator))
+        .and(warp::body::json())
+        .and(store_filter.clone())
+        .and_then(handlers::flags::review_flag_handler);
+    // Admin Handlers
+    let get_accounts = warp::get()
+        .and(path("admin"))
+        .and(path("accounts"))
+        .and(path::end())
+        .and(warp::query())
+        .and(handlers::auth::require_role(store.clone(), Role::Admin))
+        .and(store_filter.clone())
+        .and_then(handlers::accounts::get_accounts_handler);
+    let update_role = warp::put()
+        .and(path("admin"))
+        .and(path("accounts"))
+        .and(path::param::<i32>())
+        .and(path("role"))
+        .and(path::end())
+        .and(handlers::auth::require_role(store.clone(), Role::Admin))
+        .and(warp::body::json())
+        .and(store_filter.clone())
+        .and_then(handlers::accounts::update_role_handler);
+    let suspend_account = warp::put()
+        .and(path("admin"))
+        .and(path("accounts"))
+        .and(path::param::<i32>())
+        .and(path("suspension"))
+        .and(path::end())
+        .and(handlers::auth::require_role(store.clone(), Role::Admin))
+        .and(warp::body::json())
+        .and(store_filter.clone())
+        .and_then(handlers::accounts::suspend_account_handler);
+    let unsuspend_account = warp::delete()
+        .and(path("admin"))
+        .and(path("accounts"))
+        .and(path::param::<i32>())
+        .and(path("suspension"))
+        .and(path::end())
+        .and(handlers::auth::require_role(store.clone(), Role::Admin))
+        .and(store_filter.clone())
+        .and_then(handlers::accounts::unsuspend_account_handler);
+    let ban_account = warp::put()
+        .and(path("admin"))
+        .and(path("accounts"))
+        .and(path::param::<i32>())
+        .and(path("ban"))
+        .and(path::end())
+        .and(handlers::auth::require_role(store.clone(), Role::Admin))
+        .and(store_filter.clone())
+        .and_then(handlers::accounts::ban_account_handler);
+    let unban_account = warp::delete()
+        .and(path("admin"))
+        .and(path("accounts"))
+        .and(path::param::<i32>())
+        .and(path("ban"))
+        .and(path::end())
+        .and(handlers::auth::require_role(store.clone(), Role::Admin))
+        .and(store_filter.clone())
+        .and_then(handlers::accounts::unban_account_handler);
+    let delete_account = warp::delete()
+        .and(path("admin"))
+        .and(path("accounts"))
+        .and(path::param::<i32>())
+        .and(path::end())
+        .and(warp::query())
+        .and(handlers::auth::require_role(store.clone(), Role::Admin))
+        .and(store_filter.clone())
+        .and_then(handlers::accounts::delete_account_handler);
+    // Search Handlers
+    let search = warp::get()
+        .and(path("search"))
+        .and(path::end())
+        .and(warp::query())
+        .and(store_filter.clone())
+        .and_then(handlers::search::search_handler);
+    // Health Handlers
+    let health = warp::get()
+        .and(path("health"))
+        .and(path::end())
+        .and(circuit_breaker_filter)
+        .and(profanity_cache_filter)
+        .and(moderation_filter.clone())
+        .and_then(handlers::health::health_handler);
     let registration = warp::post()
         .and(path("registration"))
         .and(path::end())
         .and(warp::body::json())
         .and(store_filter.clone())
+        .and(mailer_filter.clone())
         .and_then(handlers::auth::register);
     let login = warp::post()
         .and(path("login"))
         .and(path::end())
+        .and(handlers::auth::client_ip(throttle.clone()))
         .and(warp::body::json())
         .and(store_filter.clone())
+        .and(throttle_filter.clone())
         .and_then(handlers::auth::login);
+    let refresh_token = warp::post()
+        .and(path("token"))
+        .and(path("refresh"))
+        .and(path::end())
+        .and(warp::body::json())
+        .and(store_filter.clone())
+        .and_then(handlers::auth::refresh);
+    let logout = warp::post()
+        .and(path("logout"))
+        .and(path::end())
+        .and(handlers::auth::auth(store.clone()))
+        .and(store_filter.clone())
+        .and_then(handlers::auth::logout);
+    let change_password = warp::post()
+        .and(path("account"))
+        .and(path("password"))
+        .and(path::end())
+        .and(handlers::auth::auth(store.clone()))
+        .and(handlers::auth::client_ip(throttle))
+        .and(warp::body::json())
+        .and(store_filter.clone())
+        .and(throttle_filter)
+        .and_then(handlers::password::change_password_handler);
+    let request_password_reset = warp::post()
+        .and(path("password-reset"))
+        .and(path("request"))
+        .and(path::end())
+        .and(warp::body::json())
+        .and(store_filter.clone())
+        .and(mailer_filter.clone())
+        .and_then(handlers::password::request_password_reset_handler);
+    let confirm_password_reset = warp::post()
+        .and(path("password-reset"))
+        .and(path("confirm"))
+        .and(path::end())
+        .and(warp::body::json())
+        .and(store_filter.clone())
+        .and_then(handlers::password::confirm_password_reset_handler);
+    let verify_email = warp::get()
+        .and(path("email-verification"))
+        .and(path::end())
+        .and(warp::query())
+        .and(store_filter.clone())
+        .and_then(handlers::verification::verify_email_handler);
+    let resend_verification = warp::post()
+        .and(path("email-verification"))
+        .and(path("resend"))
+        .and(path::end())
+        .and(handlers::auth::auth(store.clone()))
+        .and(store_filter.clone())
+        .and(mailer_filter.clone())
+        .and_then(handlers::verification::resend_verification_handler);
 
     // Global Routes
     let routes = get_questions
@@ -94,9 +435,38 @@ async fn main() {
         .or(add_question)
         .or(delete_question)
         .or(get_question)
+        .or(accept_answer)
+        .or(unaccept_answer)
+        .or(vote_question)
+        .or(get_tags)
+        .or(update_tag)
+        .or(get_answers)
         .or(add_answer)
+        .or(update_answer)
+        .or(delete_answer)
+        .or(vote_answer)
+        .or(flag_question)
+        .or(flag_answer)
+        .or(get_flags)
+        .or(review_flag)
+        .or(get_accounts)
+        .or(update_role)
+        .or(suspend_account)
+        .or(unsuspend_account)
+        .or(ban_account)
+        .or(unban_account)
+        .or(delete_account)
+        .or(search)
+        .or(health)
         .or(registration)
         .or(login)
+        .or(refresh_token)
+        .or(logout)
+        .or(change_password)
+        .or(request_password_reset)
+        .or(confirm_password_reset)
+        .or(verify_email)
+        .or(resend_verification)
         .with(cors)
         .with(warp::trace::request())
         .recover(error_handler);
@@ -0,0 +1,536 @@
+use handle_errors::BadWordSpan;
+use serde::Serialize;
+use std::{env, sync::Arc, time::Duration};
+use tracing::{event, Level};
+
+use crate::profanity::{BadWordsResponse, Profanity};
+use crate::store::Store;
+use crate::types::{
+    answers::Answer,
+    flags::{FlagReason, FlagTarget, NewFlag},
+    moderation::ModerationStatus,
+    questions::Question,
+};
+
+const DEFAULT_RECHECK_INTERVAL_SECS: u64 = 60;
+const DEFAULT_BAD_WORDS_THRESHOLD: i64 = 0;
+const RECHECK_BATCH_SIZE: u32 = 50;
+
+/// What to do with a post when the profanity service cannot be reached.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailurePolicy {
+    /// The post is rejected with `Error::ModerationUnavailable`
+    #[default]
+    FailClosed,
+    /// The post is published unchecked and re-checked once the service is back
+    FailOpen,
+    /// The post is stored but hidden until the service is back and it has been checked
+    HoldForReview,
+}
+
+impl FailurePolicy {
+    /// Reads the policy from the `MODERATION_FAILURE_POLICY` env variable (`fail_closed`,
+    /// `fail_open` or `hold_for_review`), defaults to `fail_closed`.
+    pub fn from_env() -> Self {
+        match env::var("MODERATION_FAILURE_POLICY")
+            .unwrap_or_default()
+            .as_str()
+        {
+            "fail_open" => FailurePolicy::FailOpen,
+            "hold_for_review" => FailurePolicy::HoldForReview,
+            "" | "fail_closed" => FailurePolicy::FailClosed,
+            other => panic!(
+                "Unknown MODERATION_FAILURE_POLICY {}, expected fail_closed, fail_open or hold_for_review.",
+                other
+            ),
+        }
+    }
+}
+
+/// What to do with a post that has more bad words than the moderation threshold.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModerationMode {
+    /// The bad words are replaced by `*`
+    #[default]
+    Censor,
+    /// The post is rejected with `Error::ContentRejected`, which lists the bad words
+    Reject,
+    /// The post is published uncensored and flagged for moderators
+    Flag,
+}
+
+impl ModerationMode {
+    /// Reads the mode from the `MODERATION_MODE` env variable (`censor`, `reject` or `flag`),
+    /// defaults to `censor`.
+    pub fn from_env() -> Self {
+        match env::var("MODERATION_MODE").unwrap_or_default().as_str() {
+            "reject" => ModerationMode::Reject,
+            "flag" => ModerationMode::Flag,
+            "" | "censor" => ModerationMode::Censor,
+            other => panic!(
+                "Unknown MODERATION_MODE {}, expected censor, reject or flag.",
+                other
+            ),
+        }
+    }
+}
+
+/// Reads the number of bad words a post can have before the moderation mode applies from the
+/// `MODERATION_BAD_WORDS_THRESHOLD` env variable, defaults to 0. Posts with fewer bad words are
+/// always censored.
+pub fn bad_words_threshold_from_env() -> i64 {
+    env::var("MODERATION_BAD_WORDS_THRESHOLD")
+        .ok()
+        .and_then(|threshold| threshold.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_BAD_WORDS_THRESHOLD)
+}
+
+/// Content that went through the moderation, censored if the profanity service was available.
+#[derive(Debug, Clone)]
+pub struct Moderated {
+    pub content: String,
+    pub status: ModerationStatus,
+}
+
+/// Applies the profanity filter to the content posted by users, falling back to the failure
+/// policy when the profanity service is unavailable.
+pub struct Moderator {
+    filter: Profanity,
+    failure_policy: FailurePolicy,
+    mode: ModerationMode,
+    bad_words_threshold: i64,
+}
+
+pub type Moderation = Arc<Moderator>;
+
+impl Moderator {
+    pub fn new(
+        filter: Profanity,
+        failure_policy: FailurePolicy,
+        mode: ModerationMode,
+        bad_words_threshold: i64,
+    ) -> Self {
+        Moderator {
+            filter,
+            failure_policy,
+            mode,
+            bad_words_threshold,
+        }
+    }
+
+    pub fn failure_policy(&self) -> FailurePolicy {
+        self.failure_policy
+    }
+
+    pub fn mode(&self) -> ModerationMode {
+        self.mode
+    }
+
+    /// Moderates a post with a single field, `field` is the name reported when the post is
+    /// rejected.
+    pub async fn moderate(
+        &self,
+        field: &'static str,
+        content: String,
+    ) -> Result<Moderated, handle_errors::Error> {
+        let check = self.check(field, content).await?;
+
+        Ok(self.review(vec![check], self.mode)?.remove(0))
+    }
+
+    /// Moderates the title and the content of a question together: they are checked in parallel
+    /// and the threshold applies to the bad words of both fields, so a rejection lists the bad
+    /// words of both.
+    pub async fn moderate_question(
+        &self,
+        title: String,
+        content: String,
+    ) -> Result<(Moderated, Moderated), handle_errors::Error> {
+        let (title, content) =
+            tokio::join!(self.check("title", title), self.check("content", content));
+        let mut moderated = self.review(vec![title?, content?], self.mode)?;
+        let content = moderated.remove(1);
+
+        Ok((moderated.remove(0), content))
+    }
+
+    /// Runs the fields of a post that was accepted while the profanity service was unavailable
+    /// through the filter. It cannot be rejected anymore, so in `Reject` mode it is flagged instead.
+    ///
+    /// The fields are checked one after the other, since a half-open circuit breaker only lets one
+    /// check through.
+    pub async fn recheck(
+        &self,
+        contents: Vec<String>,
+    ) -> Result<Vec<Moderated>, handle_errors::Error> {
+        let mut checks = Vec::with_capacity(contents.len());
+
+        for content in contents {
+            checks.push(Check::Checked("content", self.filter.check(content).await?));
+        }
+
+        let mode = match self.mode {
+            ModerationMode::Reject => ModerationMode::Flag,
+            mode => mode,
+        };
+
+        self.review(checks, mode)
+    }
+
+    /// Runs a field through the filter, falling back to the failure policy when the profanity
+    /// service is unavailable.
+    async fn check(
+        &self,
+        field: &'static str,
+        content: String,
+    ) -> Result<Check, handle_errors::Error> {
+        match self.filter.check(content.clone()).await {
+            Ok(res) => Ok(Check::Checked(field, res)),
+            Err(err) => {
+                event!(
+                    Level::WARN,
+                    policy = ?self.failure_policy,
+                    "Profanity service unavailable: {}",
+                    err
+                );
+
+                match self.failure_policy {
+                    FailurePolicy::FailClosed => Err(handle_errors::Error::ModerationUnavailable),
+                    FailurePolicy::FailOpen => Ok(Check::Unchecked(Moderated {
+                        content,
+                        status: ModerationStatus::PendingRecheck,
+                    })),
+                    FailurePolicy::HoldForReview => Ok(Check::Unchecked(Moderated {
+                        content,
+                        status: ModerationStatus::Held,
+                    })),
+                }
+            }
+        }
+    }
+
+    /// Applies the moderation mode to the fields of a post. The threshold applies to the bad words
+    /// of all the fields, not to each field.
+    fn review(
+        &self,
+        checks: Vec<Check>,
+        mode: ModerationMode,
+    ) -> Result<Vec<Moderated>, handle_errors::Error> {
+        let bad_words_total: i64 = checks
+            .iter()
+            .map(|check| match check {
+                Check::Checked(_, res) => res.bad_words_total,
+                Check::Unchecked(_) => 0,
+            })
+            .sum();
+        let mode = match bad_words_total <= self.bad_words_threshold {
+            true => ModerationMode::Censor,
+            false => mode,
+        };
+
+        if mode == ModerationMode::Reject {
+            let spans = checks
+                .into_iter()
+                .filter_map(|check| match check {
+                    Check::Checked(field, res) => Some((field, res)),
+                    Check::Unchecked(_) => None,
+                })
+                .flat_map(|(field, res)| {
+                    res.bad_words_list
+                        .into_iter()
+                        .map(move |bad_word| BadWordSpan {
+                            field: field.to_string(),
+                            original: bad_word.original,
+                            word: bad_word.word,
+                            start: bad_word.start,
+                            end: bad_word.end,
+                        })
+                })
+                .collect();
+
+            return Err(handle_errors::Error::ContentRejected(spans));
+        }
+
+        Ok(checks
+            .into_iter()
+            .map(|check| match (check, mode) {
+                (Check::Unchecked(moderated), _) => moderated,
+                (Check::Checked(_, res), ModerationMode::Flag) => Moderated {
+                    content: res.content,
+                    status: ModerationStatus::Flagged,
+                },
+                (Check::Checked(_, res), _) => Moderated {
+                    content: res.censored_content,
+                    status: ModerationStatus::Approved,
+                },
+            })
+            .collect())
+    }
+}
+
+/// Result of running a field of a post through the profanity filter.
+enum Check {
+    /// The filter checked the field, the name of the field is reported when the post is rejected
+    Checked(&'static str, BadWordsResponse),
+    /// The profanity service was unavailable and the failure policy accepted the field
+    Unchecked(Moderated),
+}
+
+/// Raises a flag on a post the moderation flagged (`ModerationStatus::Flagged`), so it shows up in
+/// the moderation queue. The post is already stored, so a failure is only logged.
+pub async fn flag_post(store: &Store, target: FlagTarget, status: ModerationStatus) {
+    if status != ModerationStatus::Flagged {
+        return;
+    }
+
+    let flag = NewFlag {
+        reason: FlagReason::Offensive,
+        details: Some(String::from(
+            "The post has more bad words than the moderation threshold.",
+        )),
+    };
+
+    match store.add_flag(target, flag, None).await {
+        // The post still has an open flag from a previous check
+        Ok(_) | Err(handle_errors::Error::AlreadyFlagged) => (),
+        Err(err) => event!(Level::ERROR, "Cannot flag post: {}", err),
+    }
+}
+
+/// Spawns a task that periodically re-checks the posts accepted while the profanity service was
+/// unavailable. The interval is read from `MODERATION_RECHECK_INTERVAL_SECS`, defaults to 60.
+pub fn spawn_recheck_worker(store: Store, moderation: Moderation) {
+    let interval_secs = env::var("MODERATION_RECHECK_INTERVAL_SECS")
+        .ok()
+        .and_then(|secs| secs.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_RECHECK_INTERVAL_SECS);
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+
+        loop {
+            interval.tick().await;
+
+            if let Err(err) = recheck(&store, &moderation).await {
+                event!(Level::WARN, "Moderation re-check stopped: {}", err);
+            }
+        }
+    });
+}
+
+/// Runs the pending posts through the profanity filter.
+///
+/// A post that cannot be re-checked is skipped and retried on the next pass. The pass only stops
+/// early when the profanity service is unavailable, since the remaining posts would fail too.
+async fn recheck(store: &Store, moderation: &Moderator) -> Result<(), handle_errors::Error> {
+    for question in store.get_questions_to_recheck(RECHECK_BATCH_SIZE).await? {
+        match recheck_question(store, moderation, &question).await {
+            Ok(_) => event!(
+                Level::INFO,
+                question_id = question.id.0,
+                "Question re-checked"
+            ),
+            Err(handle_errors::Error::ModerationUnavailable) => {
+                return Err(handle_errors::Error::ModerationUnavailable)
+            }
+            Err(err) => event!(
+                Level::WARN,
+                question_id = question.id.0,
+                "Cannot re-check question: {}",
+                err
+            ),
+        }
+    }
+
+    for answer in store.get_answers_to_recheck(RECHECK_BATCH_SIZE).await? {
+        match recheck_answer(store, moderation, &answer).await {
+            Ok(_) => event!(Level::INFO, answer_id = answer.id.0, "Answer re-checked"),
+            Err(handle_errors::Error::ModerationUnavailable) => {
+                return Err(handle_errors::Error::ModerationUnavailable)
+            }
+            Err(err) => event!(
+                Level::WARN,
+                answer_id = answer.id.0,
+                "Cannot re-check answer: {}",
+                err
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+async fn recheck_question(
+    store: &Store,
+    moderation: &Moderator,
+    question: &Question,
+) -> Result<(), handle_errors::Error> {
+    let mut moderated = moderation
+        .recheck(vec![question.title.clone(), question.content.clone()])
+        .await?;
+    let content = moderated.remove(1);
+    let title = moderated.remove(0);
+    let status = title.status.max(content.status);
+    let updated = store
+        .update_question_moderation(question, title.content, content.content, status)
+        .await?;
+
+    if updated {
+        flag_post(store, FlagTarget::Question(question.id.clone()), status).await;
+    }
+
+    Ok(())
+}
+
+async fn recheck_answer(
+    store: &Store,
+    moderation: &Moderator,
+    answer: &Answer,
+) -> Result<(), handle_errors::Error> {
+    let content = moderation
+        .recheck(vec![answer.content.clone()])
+        .await?
+        .remove(0);
+    let updated = store
+        .update_answer_moderation(answer, content.content, content.status)
+        .await?;
+
+    if updated {
+        flag_post(store, FlagTarget::Answer(answer.id.clone()), content.status).await;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod moderation_tests {
+    use async_trait::async_trait;
+    use std::sync::Arc;
+
+    use super::{FailurePolicy, ModerationMode, Moderator};
+    use crate::profanity::{BadWordsResponse, ProfanityFilter, WordListFilter};
+    use crate::types::moderation::ModerationStatus;
+
+    struct UnavailableFilter;
+
+    #[async_trait]
+    impl ProfanityFilter for UnavailableFilter {
+        async fn check(&self, _content: String) -> Result<BadWordsResponse, handle_errors::Error> {
+            Err(handle_errors::Error::ModerationUnavailable)
+        }
+    }
+
+    fn moderator(failure_policy: FailurePolicy) -> Moderator {
+        Moderator::new(
+            Arc::new(UnavailableFilter),
+            failure_policy,
+            ModerationMode::Censor,
+            0,
+        )
+    }
+
+    fn word_list_moderator(mode: ModerationMode, bad_words_threshold: i64) -> Moderator {
+        Moderator::new(
+            Arc::new(WordListFilter::new(["damn", "shit"])),
+            FailurePolicy::FailClosed,
+            mode,
+            bad_words_threshold,
+        )
+    }
+
+    #[tokio::test]
+    async fn reject_when_fail_closed() {
+        let res = moderator(FailurePolicy::FailClosed)
+            .moderate("content", String::from("content"))
+            .await;
+
+        assert_eq!(
+            format!("{}", res.unwrap_err()),
+            format!("{}", handle_errors::Error::ModerationUnavailable)
+        );
+    }
+
+    #[tokio::test]
+    async fn accept_unchecked_content_when_fail_open() {
+        let res = moderator(FailurePolicy::FailOpen)
+            .moderate("content", String::from("content"))
+            .await
+            .unwrap();
+
+        assert_eq!(res.content, "content");
+        assert_eq!(res.status, ModerationStatus::PendingRecheck);
+    }
+
+    #[tokio::test]
+    async fn hold_content_when_hold_for_review() {
+        let res = moderator(FailurePolicy::HoldForReview)
+            .moderate("content", String::from("content"))
+            .await
+            .unwrap();
+
+        assert_eq!(res.status, ModerationStatus::Held);
+    }
+
+    #[tokio::test]
+    async fn censor_below_threshold() {
+        let res = word_list_moderator(ModerationMode::Reject, 1)
+            .moderate("content", String::from("damn it"))
+            .await
+            .unwrap();
+
+        assert_eq!(res.content, "**** it");
+        assert_eq!(res.status, ModerationStatus::Approved);
+    }
+
+    #[tokio::test]
+    async fn reject_title_and_content_above_threshold() {
+        let res = word_list_moderator(ModerationMode::Reject, 0)
+            .moderate_question(String::from("damn"), String::from("oh shit"))
+            .await;
+
+        match res {
+            Err(handle_errors::Error::ContentRejected(spans)) => {
+                assert_eq!(spans.len(), 2);
+                assert_eq!(spans[0].field, "title");
+                assert_eq!(spans[1].field, "content");
+                assert_eq!(spans[1].original, "shit");
+                assert_eq!((spans[1].start, spans[1].end), (3, 7));
+            }
+            res => panic!("unexpected moderation result: {:?}", res),
+        }
+    }
+
+    #[tokio::test]
+    async fn flag_uncensored_content_above_threshold() {
+        let res = word_list_moderator(ModerationMode::Flag, 0)
+            .moderate("content", String::from("damn it"))
+            .await
+            .unwrap();
+
+        assert_eq!(res.content, "damn it");
+        assert_eq!(res.status, ModerationStatus::Flagged);
+    }
+
+    #[tokio::test]
+    async fn threshold_applies_to_the_whole_post() {
+        let moderator = word_list_moderator(ModerationMode::Reject, 1);
+        let res = moderator
+            .moderate_question(String::from("damn"), String::from("oh shit"))
+            .await;
+
+        match res {
+            Err(handle_errors::Error::ContentRejected(spans)) => assert_eq!(spans.len(), 2),
+            res => panic!("unexpected moderation result: {:?}", res),
+        }
+
+        let (title, content) = moderator
+            .moderate_question(String::from("damn"), String::from("oh no"))
+            .await
+            .unwrap();
+
+        assert_eq!(title.content, "****");
+        assert_eq!(content.status, ModerationStatus::Approved);
+    }
+}
@@ -0,0 +1,184 @@
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
+use reqwest_retry::{policies::ExponentialBackoff, RetryTransientMiddleware};
+use serde::{Deserialize, Serialize};
+use std::{env, time::Duration};
+
+use super::{BadWordsResponse, ProfanityFilter};
+
+const DEFAULT_API_URL: &str = "https://api.apilayer.com/bad_words";
+const DEFAULT_TIMEOUT_MS: u64 = 5000;
+const DEFAULT_MAX_RETRIES: u32 = 5;
+/// Characters of a non JSON error response kept in the error message
+const MAX_ERROR_MESSAGE_LENGTH: usize = 200;
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct APIResponse {
+    pub message: String,
+}
+
+/// Configuration of the APILayer client.
+#[derive(Debug, Clone)]
+pub struct ApiLayerConfig {
+    pub api_key: String,
+    /// URL of the bad words endpoint
+    pub url: String,
+    /// Timeout of every request (each retry has its own timeout)
+    pub timeout: Duration,
+    /// Number of retries of a request failing with a transient error
+    pub max_retries: u32,
+}
+
+impl ApiLayerConfig {
+    /// Reads the configuration from the env variables:
+    ///
+    /// - `BAD_WORDS_API_KEY` (required)
+    /// - `BAD_WORDS_API_URL`, defaults to `https://api.apilayer.com/bad_words`
+    /// - `BAD_WORDS_API_TIMEOUT_MS`, defaults to 5000
+    /// - `BAD_WORDS_API_MAX_RETRIES`, defaults to 5
+    pub fn from_env() -> Result<Self, handle_errors::Error> {
+        let api_key =
+            env::var("BAD_WORDS_API_KEY").map_err(|_| handle_errors::Error::EnvVariableError)?;
+        let url = env::var("BAD_WORDS_API_URL").unwrap_or(String::from(DEFAULT_API_URL));
+        let timeout_ms = env::var("BAD_WORDS_API_TIMEOUT_MS")
+            .ok()
+            .and_then(|timeout| timeout.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_TIMEOUT_MS);
+        let max_retries = env::var("BAD_WORDS_API_MAX_RETRIES")
+            .ok()
+            .and_then(|retries| retries.parse::<u32>().ok())
+            .unwrap_or(DEFAULT_MAX_RETRIES);
+
+        Ok(ApiLayerConfig {
+            api_key,
+            url,
+            timeout: Duration::from_millis(timeout_ms),
+            max_retries,
+        })
+    }
+}
+
+/// Profanity filter backed by the [APILayer Bad Words API](https://apilayer.com/marketplace/bad_words-api).
+///
+/// The HTTP client and its retry policy are created once, so every check reuses the connection
+/// pool and TLS sessions of the client.
+#[derive(Clone)]
+pub struct ApiLayerFilter {
+    client: ClientWithMiddleware,
+    api_key: String,
+    url: String,
+}
+
+impl ApiLayerFilter {
+    pub fn new(config: ApiLayerConfig) -> Result<Self, handle_errors::Error> {
+        let retry_policy = ExponentialBackoff::builder().build_with_max_retries(config.max_retries);
+        let http_client = reqwest::Client::builder()
+            .timeout(config.timeout)
+            .build()
+            .map_err(handle_errors::Error::ReqwestAPIError)?;
+        let client = ClientBuilder::new(http_client)
+            .with(RetryTransientMiddleware::new_with_policy(retry_policy))
+            .build();
+
+        Ok(ApiLayerFilter {
+            client,
+            api_key: config.api_key,
+            url: config.url,
+        })
+    }
+}
+
+#[async_trait]
+impl ProfanityFilter for ApiLayerFilter {
+    async fn check(&self, content: String) -> Result<BadWordsResponse, handle_errors::Error> {
+        let res = self
+            .client
+            .post(&self.url)
+            .query(&[("censor_character", "*")])
+            .header("apikey", &self.api_key)
+            .body(content)
+            .send()
+            .await
+            .map_err(handle_errors::Error::MiddlewareReqwestAPIError)?;
+        let res_status = res.status();
+
+        if !res_status.is_success() {
+            let err = transform_error(res).await;
+
+            if res_status.is_client_error() {
+                return Err(handle_errors::Error::ClientError(err));
+            }
+
+            return Err(handle_errors::Error::ServerError(err));
+        }
+
+        res.json::<BadWordsResponse>()
+            .await
+            .map_err(handle_errors::Error::ReqwestAPIError)
+    }
+}
+
+async fn transform_error(res: reqwest::Response) -> handle_errors::ApiLayerError {
+    let status = res.status();
+    let body = res.text().await.unwrap_or_default();
+
+    handle_errors::ApiLayerError {
+        status: status.as_u16(),
+        message: error_message(status, &body),
+    }
+}
+
+/// Returns the message of an error response. Proxies in front of APILayer can reply with HTML or
+/// plain text instead of JSON, then the start of the body or the reason of the status is used.
+fn error_message(status: StatusCode, body: &str) -> String {
+    if let Ok(res) = serde_json::from_str::<APIResponse>(body) {
+        return res.message;
+    }
+
+    let body = body.trim();
+
+    if body.is_empty() {
+        return status
+            .canonical_reason()
+            .unwrap_or("Unknown error")
+            .to_string();
+    }
+
+    body.chars().take(MAX_ERROR_MESSAGE_LENGTH).collect()
+}
+
+#[cfg(test)]
+mod apilayer_tests {
+    use reqwest::StatusCode;
+
+    use super::{error_message, MAX_ERROR_MESSAGE_LENGTH};
+
+    #[test]
+    fn read_json_error_message() {
+        assert_eq!(
+            error_message(
+                StatusCode::UNAUTHORIZED,
+                r#"{"message": "Invalid API key"}"#
+            ),
+            "Invalid API key"
+        );
+    }
+
+    #[test]
+    fn fall_back_to_body_or_status() {
+        let html = "<html><body>502 Bad Gateway</body></html>";
+
+        assert_eq!(error_message(StatusCode::BAD_GATEWAY, html), html);
+        assert_eq!(
+            error_message(StatusCode::SERVICE_UNAVAILABLE, " "),
+            "Service Unavailable"
+        );
+        assert_eq!(
+            error_message(StatusCode::BAD_GATEWAY, &"x".repeat(1000))
+                .chars()
+                .count(),
+            MAX_ERROR_MESSAGE_LENGTH
+        );
+    }
+}
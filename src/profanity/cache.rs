@@ -0,0 +1,238 @@
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{HashMap, VecDeque},
+    env,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+use super::{BadWordsResponse, Profanity, ProfanityFilter};
+
+const DEFAULT_CAPACITY: usize = 10_000;
+const DEFAULT_TTL_SECS: u64 = 3600;
+
+type ContentHash = [u8; 32];
+
+#[derive(Debug, Clone)]
+pub struct CacheConfig {
+    /// Maximum number of cached results, the oldest ones are evicted first
+    pub capacity: usize,
+    /// Time a result is valid for
+    pub ttl: Duration,
+}
+
+impl CacheConfig {
+    /// Reads the configuration from the env variables:
+    ///
+    /// - `PROFANITY_CACHE_CAPACITY`, defaults to 10000
+    /// - `PROFANITY_CACHE_TTL_SECS`, defaults to 3600
+    pub fn from_env() -> Self {
+        let capacity = env::var("PROFANITY_CACHE_CAPACITY")
+            .ok()
+            .and_then(|capacity| capacity.parse::<usize>().ok())
+            .unwrap_or(DEFAULT_CAPACITY);
+        let ttl_secs = env::var("PROFANITY_CACHE_TTL_SECS")
+            .ok()
+            .and_then(|secs| secs.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_TTL_SECS);
+
+        CacheConfig {
+            capacity,
+            ttl: Duration::from_secs(ttl_secs),
+        }
+    }
+}
+
+/// Counters of the cache reported by the health endpoint.
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheHealth {
+    pub entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    results: HashMap<ContentHash, (Instant, BadWordsResponse)>,
+    /// Hashes in insertion order, used to evict the oldest results when the cache is full
+    order: VecDeque<ContentHash>,
+}
+
+/// Profanity filter that remembers the results of the inner filter, so the same content is not
+/// sent to the paid profanity service again.
+///
+/// Results are keyed by the SHA-256 hash of the content. Only successful checks are cached.
+pub struct ProfanityCache {
+    inner: Profanity,
+    config: CacheConfig,
+    entries: Mutex<Entries>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl ProfanityCache {
+    pub fn new(inner: Profanity, config: CacheConfig) -> Self {
+        ProfanityCache {
+            inner,
+            config,
+            entries: Mutex::new(Entries::default()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    pub fn health(&self) -> CacheHealth {
+        CacheHealth {
+            entries: self.entries.lock().results.len(),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    fn get(&self, hash: &ContentHash) -> Option<BadWordsResponse> {
+        let mut entries = self.entries.lock();
+
+        match entries.results.get(hash) {
+            Some((inserted_at, res)) if inserted_at.elapsed() < self.config.ttl => {
+                Some(res.clone())
+            }
+            Some(_) => {
+                entries.results.remove(hash);
+                entries.order.retain(|key| key != hash);
+
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn insert(&self, hash: ContentHash, res: BadWordsResponse) {
+        if self.config.capacity == 0 {
+            return;
+        }
+
+        let mut entries = self.entries.lock();
+
+        if entries
+            .results
+            .insert(hash, (Instant::now(), res))
+            .is_some()
+        {
+            entries.order.retain(|key| *key != hash);
+        }
+
+        entries.order.push_back(hash);
+
+        while entries.results.len() > self.config.capacity {
+            match entries.order.pop_front() {
+                Some(oldest) => {
+                    entries.results.remove(&oldest);
+                }
+                None => break,
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl ProfanityFilter for ProfanityCache {
+    async fn check(&self, content: String) -> Result<BadWordsResponse, handle_errors::Error> {
+        let hash: ContentHash = Sha256::digest(content.as_bytes()).into();
+
+        if let Some(res) = self.get(&hash) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+
+            return Ok(res);
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        let res = self.inner.check(content).await?;
+
+        self.insert(hash, res.clone());
+
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod cache_tests {
+    use async_trait::async_trait;
+    use std::{
+        sync::{
+            atomic::{AtomicU32, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use super::{BadWordsResponse, CacheConfig, ProfanityCache, ProfanityFilter};
+
+    /// Filter that counts its calls
+    #[derive(Default)]
+    struct CountingFilter {
+        calls: AtomicU32,
+    }
+
+    #[async_trait]
+    impl ProfanityFilter for CountingFilter {
+        async fn check(&self, content: String) -> Result<BadWordsResponse, handle_errors::Error> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+
+            Ok(BadWordsResponse {
+                content: content.clone(),
+                bad_words_total: 0,
+                bad_words_list: vec![],
+                censored_content: content,
+            })
+        }
+    }
+
+    fn cache(filter: Arc<CountingFilter>, capacity: usize, ttl: Duration) -> ProfanityCache {
+        ProfanityCache::new(filter, CacheConfig { capacity, ttl })
+    }
+
+    #[tokio::test]
+    async fn reuse_cached_results() {
+        let filter = Arc::new(CountingFilter::default());
+        let cache = cache(filter.clone(), 10, Duration::from_secs(60));
+
+        cache.check(String::from("a")).await.unwrap();
+        cache.check(String::from("a")).await.unwrap();
+        cache.check(String::from("b")).await.unwrap();
+
+        assert_eq!(filter.calls.load(Ordering::SeqCst), 2);
+        assert_eq!(cache.health().hits, 1);
+        assert_eq!(cache.health().misses, 2);
+    }
+
+    #[tokio::test]
+    async fn expire_cached_results() {
+        let filter = Arc::new(CountingFilter::default());
+        let cache = cache(filter.clone(), 10, Duration::ZERO);
+
+        cache.check(String::from("a")).await.unwrap();
+        cache.check(String::from("a")).await.unwrap();
+
+        assert_eq!(filter.calls.load(Ordering::SeqCst), 2);
+        assert_eq!(cache.health().hits, 0);
+    }
+
+    #[tokio::test]
+    async fn evict_oldest_results() {
+        let filter = Arc::new(CountingFilter::default());
+        let cache = cache(filter.clone(), 2, Duration::from_secs(60));
+
+        cache.check(String::from("a")).await.unwrap();
+        cache.check(String::from("b")).await.unwrap();
+        cache.check(String::from("c")).await.unwrap();
+        cache.check(String::from("c")).await.unwrap();
+        cache.check(String::from("a")).await.unwrap();
+
+        assert_eq!(cache.health().entries, 2);
+        assert_eq!(filter.calls.load(Ordering::SeqCst), 4);
+    }
+}
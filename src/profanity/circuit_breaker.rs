@@ -0,0 +1,382 @@
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::{
+    env,
+    time::{Duration, Instant},
+};
+use tracing::{event, Level};
+
+use super::{BadWordsResponse, Profanity, ProfanityFilter};
+
+const DEFAULT_FAILURE_THRESHOLD: u32 = 5;
+const DEFAULT_RESET_TIMEOUT_SECS: u64 = 30;
+
+#[derive(Debug, Clone)]
+pub struct CircuitBreakerConfig {
+    /// Number of consecutive failures that opens the circuit
+    pub failure_threshold: u32,
+    /// Time the circuit stays open before letting a trial request through
+    pub reset_timeout: Duration,
+}
+
+impl CircuitBreakerConfig {
+    /// Reads the configuration from the env variables:
+    ///
+    /// - `PROFANITY_CIRCUIT_FAILURE_THRESHOLD`, defaults to 5
+    /// - `PROFANITY_CIRCUIT_RESET_SECS`, defaults to 30
+    pub fn from_env() -> Self {
+        let failure_threshold = env::var("PROFANITY_CIRCUIT_FAILURE_THRESHOLD")
+            .ok()
+            .and_then(|threshold| threshold.parse::<u32>().ok())
+            .unwrap_or(DEFAULT_FAILURE_THRESHOLD);
+        let reset_timeout_secs = env::var("PROFANITY_CIRCUIT_RESET_SECS")
+            .ok()
+            .and_then(|secs| secs.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_RESET_TIMEOUT_SECS);
+
+        CircuitBreakerConfig {
+            failure_threshold,
+            reset_timeout: Duration::from_secs(reset_timeout_secs),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    /// Requests go through to the profanity service
+    Closed,
+    /// Requests fail immediately without calling the profanity service
+    Open,
+    /// A single trial request is let through to find out if the service is back. If the trial does
+    /// not finish within `reset_timeout` (e.g. its request was dropped) another one is let through
+    HalfOpen,
+}
+
+/// State of the circuit breaker reported by the health endpoint.
+#[derive(Debug, Clone, Serialize)]
+pub struct CircuitBreakerHealth {
+    pub state: CircuitState,
+    pub consecutive_failures: u32,
+}
+
+#[derive(Debug)]
+struct BreakerState {
+    state: CircuitState,
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    /// When the current trial request was let through
+    probe_started_at: Option<Instant>,
+}
+
+/// Profanity filter that stops calling a failing profanity service.
+///
+/// After `failure_threshold` consecutive failures the circuit opens and every check fails with
+/// `Error::ModerationUnavailable` until `reset_timeout` has passed. Then a single trial check is let
+/// through: the circuit closes again if it succeeds or it stays open for another `reset_timeout`.
+/// A trial that never finishes (e.g. its request was dropped) is replaced by a new one after
+/// `reset_timeout`.
+///
+/// Only transport errors and server errors count as failures. Client errors (e.g. a post too long
+/// for the service) are caused by the request, not by the service, so they leave the state as is.
+pub struct CircuitBreaker {
+    inner: Profanity,
+    config: CircuitBreakerConfig,
+    state: Mutex<BreakerState>,
+}
+
+impl CircuitBreaker {
+    pub fn new(inner: Profanity, config: CircuitBreakerConfig) -> Self {
+        CircuitBreaker {
+            inner,
+            config,
+            state: Mutex::new(BreakerState {
+                state: CircuitState::Closed,
+                consecutive_failures: 0,
+                opened_at: None,
+                probe_started_at: None,
+            }),
+        }
+    }
+
+    pub fn health(&self) -> CircuitBreakerHealth {
+        let state = self.state.lock();
+
+        CircuitBreakerHealth {
+            state: state.state,
+            consecutive_failures: state.consecutive_failures,
+        }
+    }
+
+    /// Returns true when a check can call the profanity service.
+    fn acquire(&self) -> bool {
+        let mut state = self.state.lock();
+
+        match state.state {
+            CircuitState::Closed => true,
+            CircuitState::HalfOpen => {
+                // The trial never reported its outcome, most likely because it was dropped
+                let stale = state
+                    .probe_started_at
+                    .is_none_or(|started_at| started_at.elapsed() >= self.config.reset_timeout);
+
+                if stale {
+                    event!(
+                        Level::INFO,
+                        "Profanity circuit breaker trial timed out, retrying"
+                    );
+                    state.probe_started_at = Some(Instant::now());
+                }
+
+                stale
+            }
+            CircuitState::Open => {
+                let reset = state
+                    .opened_at
+                    .is_none_or(|opened_at| opened_at.elapsed() >= self.config.reset_timeout);
+
+                if reset {
+                    event!(Level::INFO, "Profanity circuit breaker is half-open");
+                    state.state = CircuitState::HalfOpen;
+                    state.probe_started_at = Some(Instant::now());
+                }
+
+                reset
+            }
+        }
+    }
+
+    fn record_success(&self) {
+        let mut state = self.state.lock();
+
+        if state.state != CircuitState::Closed {
+            event!(Level::INFO, "Profanity circuit breaker is closed");
+        }
+
+        state.state = CircuitState::Closed;
+        state.consecutive_failures = 0;
+        state.opened_at = None;
+        state.probe_started_at = None;
+    }
+
+    fn record_failure(&self) {
+        let mut state = self.state.lock();
+
+        state.consecutive_failures += 1;
+
+        if state.state == CircuitState::HalfOpen
+            || state.consecutive_failures >= self.config.failure_threshold
+        {
+            if state.state != CircuitState::Open {
+                event!(
+                    Level::WARN,
+                    consecutive_failures = state.consecutive_failures,
+                    "Profanity circuit breaker is open"
+                );
+            }
+
+            state.state = CircuitState::Open;
+            state.opened_at = Some(Instant::now());
+            state.probe_started_at = None;
+        }
+    }
+
+    /// Lets the next check be the trial when the current trial failed for reasons unrelated to the
+    /// health of the service.
+    fn release_probe(&self) {
+        self.state.lock().probe_started_at = None;
+    }
+}
+
+#[async_trait]
+impl ProfanityFilter for CircuitBreaker {
+    async fn check(&self, content: String) -> Result<BadWordsResponse, handle_errors::Error> {
+        if !self.acquire() {
+            return Err(handle_errors::Error::ModerationUnavailable);
+        }
+
+        match self.inner.check(content).await {
+            Ok(res) => {
+                self.record_success();
+
+                Ok(res)
+            }
+            Err(err @ handle_errors::Error::ClientError(_)) => {
+                event!(Level::WARN, "Profanity check rejected: {}", err);
+                self.release_probe();
+
+                Err(err)
+            }
+            Err(err) => {
+                event!(Level::ERROR, "Profanity check failed: {}", err);
+                self.record_failure();
+
+                Err(err)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod circuit_breaker_tests {
+    use async_trait::async_trait;
+    use std::{
+        sync::{
+            atomic::{AtomicBool, AtomicU32, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use super::{
+        BadWordsResponse, CircuitBreaker, CircuitBreakerConfig, CircuitState, ProfanityFilter,
+    };
+
+    /// Filter that fails while `failing` is true, rejects the content while `rejecting` is true,
+    /// never answers while `hanging` is true and counts its calls
+    #[derive(Default)]
+    struct FlakyFilter {
+        failing: AtomicBool,
+        rejecting: AtomicBool,
+        hanging: AtomicBool,
+        calls: AtomicU32,
+    }
+
+    #[async_trait]
+    impl ProfanityFilter for FlakyFilter {
+        async fn check(&self, content: String) -> Result<BadWordsResponse, handle_errors::Error> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+
+            if self.hanging.load(Ordering::SeqCst) {
+                std::future::pending::<()>().await;
+            }
+
+            if self.failing.load(Ordering::SeqCst) {
+                return Err(handle_errors::Error::EnvVariableError);
+            }
+
+            if self.rejecting.load(Ordering::SeqCst) {
+                return Err(handle_errors::Error::ClientError(
+                    handle_errors::ApiLayerError {
+                        status: 413,
+                        message: String::from("Content too large"),
+                    },
+                ));
+            }
+
+            Ok(BadWordsResponse {
+                content: content.clone(),
+                bad_words_total: 0,
+                bad_words_list: vec![],
+                censored_content: content,
+            })
+        }
+    }
+
+    fn breaker(filter: Arc<FlakyFilter>, reset_timeout: Duration) -> CircuitBreaker {
+        CircuitBreaker::new(
+            filter,
+            CircuitBreakerConfig {
+                failure_threshold: 2,
+                reset_timeout,
+            },
+        )
+    }
+
+    #[tokio::test]
+    async fn open_after_consecutive_failures() {
+        let filter = Arc::new(FlakyFilter::default());
+        let breaker = breaker(filter.clone(), Duration::from_secs(60));
+
+        filter.failing.store(true, Ordering::SeqCst);
+
+        assert!(breaker.check(String::from("a")).await.is_err());
+        assert_eq!(breaker.health().state, CircuitState::Closed);
+        assert!(breaker.check(String::from("a")).await.is_err());
+        assert_eq!(breaker.health().state, CircuitState::Open);
+
+        filter.failing.store(false, Ordering::SeqCst);
+
+        let res = breaker.check(String::from("a")).await;
+
+        assert_eq!(
+            format!("{}", res.unwrap_err()),
+            format!("{}", handle_errors::Error::ModerationUnavailable)
+        );
+        assert_eq!(filter.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn ignore_client_errors() {
+        let filter = Arc::new(FlakyFilter::default());
+        let breaker = breaker(filter.clone(), Duration::from_secs(60));
+
+        filter.failing.store(true, Ordering::SeqCst);
+        assert!(breaker.check(String::from("a")).await.is_err());
+        filter.failing.store(false, Ordering::SeqCst);
+        filter.rejecting.store(true, Ordering::SeqCst);
+
+        for _ in 0..3 {
+            let res = breaker.check(String::from("a")).await;
+
+            assert!(matches!(res, Err(handle_errors::Error::ClientError(_))));
+        }
+
+        assert_eq!(breaker.health().state, CircuitState::Closed);
+        assert_eq!(breaker.health().consecutive_failures, 1);
+        assert_eq!(filter.calls.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn close_after_successful_trial() {
+        let filter = Arc::new(FlakyFilter::default());
+        let breaker = breaker(filter.clone(), Duration::ZERO);
+
+        filter.failing.store(true, Ordering::SeqCst);
+
+        assert!(breaker.check(String::from("a")).await.is_err());
+        assert!(breaker.check(String::from("a")).await.is_err());
+        assert_eq!(breaker.health().state, CircuitState::Open);
+
+        filter.failing.store(false, Ordering::SeqCst);
+
+        assert!(breaker.check(String::from("a")).await.is_ok());
+        assert_eq!(breaker.health().state, CircuitState::Closed);
+        assert_eq!(breaker.health().consecutive_failures, 0);
+    }
+
+    #[tokio::test]
+    async fn retry_after_dropped_trial() {
+        let filter = Arc::new(FlakyFilter::default());
+        let breaker = breaker(filter.clone(), Duration::from_millis(50));
+
+        filter.failing.store(true, Ordering::SeqCst);
+
+        assert!(breaker.check(String::from("a")).await.is_err());
+        assert!(breaker.check(String::from("a")).await.is_err());
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        filter.failing.store(false, Ordering::SeqCst);
+        filter.hanging.store(true, Ordering::SeqCst);
+
+        // The trial is dropped before the filter answers
+        let trial =
+            tokio::time::timeout(Duration::from_millis(5), breaker.check(String::from("a"))).await;
+
+        assert!(trial.is_err());
+        assert_eq!(breaker.health().state, CircuitState::HalfOpen);
+
+        filter.hanging.store(false, Ordering::SeqCst);
+
+        assert_eq!(
+            format!("{}", breaker.check(String::from("a")).await.unwrap_err()),
+            format!("{}", handle_errors::Error::ModerationUnavailable)
+        );
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        assert!(breaker.check(String::from("a")).await.is_ok());
+        assert_eq!(breaker.health().state, CircuitState::Closed);
+    }
+}
@@ -0,0 +1,48 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+pub mod apilayer;
+pub mod cache;
+pub mod circuit_breaker;
+pub mod word_list;
+
+pub use apilayer::{ApiLayerConfig, ApiLayerFilter};
+pub use cache::{CacheConfig, ProfanityCache};
+pub use circuit_breaker::{CircuitBreaker, CircuitBreakerConfig};
+pub use word_list::WordListFilter;
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct BadWord {
+    pub original: String,
+    pub word: String,
+    pub deviations: i64,
+    pub info: i64,
+    #[serde(rename = "replacedLen")]
+    pub replaced_len: i64,
+    /// Character position of the bad word in the content
+    #[serde(default)]
+    pub start: i64,
+    #[serde(default)]
+    pub end: i64,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct BadWordsResponse {
+    pub content: String,
+    pub bad_words_total: i64,
+    pub bad_words_list: Vec<BadWord>,
+    pub censored_content: String,
+}
+
+/// Service that detects and censors bad words of the content posted by users.
+///
+/// Implementations are wrapped by the `Moderator` (see `crate::moderation`), which decides what to do
+/// with the bad words, so the backend can be changed without touching the handlers.
+#[async_trait]
+pub trait ProfanityFilter: Send + Sync {
+    /// Returns the bad words found in the content together with the censored content.
+    async fn check(&self, content: String) -> Result<BadWordsResponse, handle_errors::Error>;
+}
+
+pub type Profanity = Arc<dyn ProfanityFilter>;
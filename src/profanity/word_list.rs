@@ -0,0 +1,254 @@
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::{fs, io, path::Path};
+
+use super::{BadWord, BadWordsResponse, ProfanityFilter};
+
+/// Letters of a word together with the number of times they are repeated in a row
+type LetterRuns = Vec<(char, usize)>;
+
+/// Offline profanity filter which censors the words of a word list.
+///
+/// Words are matched case insensitively and it also detects the most common ways of disguising a
+/// word: leetspeak (`sh1t`, `@ss`) and repeated letters (`shiiiit`). Like the APILayer service, every
+/// substituted or repeated letter counts as a deviation of the reported `BadWord`.
+#[derive(Debug, Clone, Default)]
+pub struct WordListFilter {
+    /// Words of the list grouped by their letters without repetitions, e.g. `ass` is stored as
+    /// `as => [("ass", [('a', 1), ('s', 2)])]`
+    words: HashMap<String, Vec<(String, LetterRuns)>>,
+}
+
+impl WordListFilter {
+    pub fn new<I, S>(words: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut filter = WordListFilter::default();
+
+        for word in words {
+            let word = word.as_ref().trim().to_lowercase();
+
+            if word.is_empty() {
+                continue;
+            }
+
+            let (letters, _) = deobfuscate(&word);
+            let runs = letter_runs(&letters);
+            let key = runs.iter().map(|(letter, _)| letter).collect::<String>();
+
+            filter.words.entry(key).or_default().push((word, runs));
+        }
+
+        filter
+    }
+
+    /// Reads a word list file with one word per line. Empty lines and lines starting with `#` are ignored.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let content = fs::read_to_string(path)?;
+
+        Ok(WordListFilter::new(content.lines().filter(|line| {
+            let line = line.trim();
+
+            !line.is_empty() && !line.starts_with('#')
+        })))
+    }
+
+    /// Returns the word of the list matching a token and the deviations between both.
+    fn find(&self, token: &str) -> Option<(String, i64)> {
+        let (letters, substitutions) = deobfuscate(token);
+        let runs = letter_runs(&letters);
+        let key = runs.iter().map(|(letter, _)| letter).collect::<String>();
+
+        self.words.get(&key)?.iter().find_map(|(word, word_runs)| {
+            // A token matches when every letter is repeated at least as many times as in the word,
+            // so `ass` does not match `as` but it does match `asssss`.
+            let matches = runs
+                .iter()
+                .zip(word_runs)
+                .all(|((_, count), (_, word_count))| count >= word_count);
+
+            if !matches {
+                return None;
+            }
+
+            let repetitions: usize = runs
+                .iter()
+                .zip(word_runs)
+                .map(|((_, count), (_, word_count))| count - word_count)
+                .sum();
+
+            Some((word.clone(), (substitutions + repetitions) as i64))
+        })
+    }
+}
+
+#[async_trait]
+impl ProfanityFilter for WordListFilter {
+    async fn check(&self, content: String) -> Result<BadWordsResponse, handle_errors::Error> {
+        let mut bad_words_list = Vec::new();
+        let mut censored_content = String::with_capacity(content.len());
+        let mut last_end = 0;
+
+        for (start, end) in tokens(&content) {
+            let token = &content[start..end];
+            // Punctuation after a word ("damn!") is only censored if the whole token is a bad word
+            let trimmed = token.trim_end_matches(|c: char| !c.is_alphanumeric());
+            let found = self
+                .find(token)
+                .map(|found| (token, found))
+                .or_else(|| self.find(trimmed).map(|found| (trimmed, found)));
+
+            if let Some((original, (word, deviations))) = found {
+                let replaced_len = original.chars().count();
+                let start_char = content[..start].chars().count();
+
+                censored_content.push_str(&content[last_end..start]);
+                censored_content.push_str(&"*".repeat(replaced_len));
+                last_end = start + original.len();
+
+                bad_words_list.push(BadWord {
+                    original: original.to_string(),
+                    word,
+                    deviations,
+                    info: 0,
+                    replaced_len: replaced_len as i64,
+                    start: start_char as i64,
+                    end: (start_char + replaced_len) as i64,
+                });
+            }
+        }
+
+        censored_content.push_str(&content[last_end..]);
+
+        Ok(BadWordsResponse {
+            bad_words_total: bad_words_list.len() as i64,
+            bad_words_list,
+            censored_content,
+            content,
+        })
+    }
+}
+
+/// Characters that can be part of a word, including the symbols used in leetspeak.
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '@' | '$' | '!')
+}
+
+/// Returns the byte ranges of the words of the content.
+fn tokens(content: &str) -> Vec<(usize, usize)> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+
+    for (index, c) in content.char_indices() {
+        match (is_word_char(c), start) {
+            (true, None) => start = Some(index),
+            (false, Some(token_start)) => {
+                tokens.push((token_start, index));
+                start = None;
+            }
+            _ => (),
+        }
+    }
+
+    if let Some(token_start) = start {
+        tokens.push((token_start, content.len()));
+    }
+
+    tokens
+}
+
+/// Lowercases a word and replaces the leetspeak characters by the letters they represent.
+///
+/// Returns the resulting letters and the number of replaced characters.
+fn deobfuscate(word: &str) -> (String, usize) {
+    let mut substitutions = 0;
+    let letters = word
+        .to_lowercase()
+        .chars()
+        .map(|c| {
+            let letter = match c {
+                '0' => 'o',
+                '1' | '!' => 'i',
+                '3' => 'e',
+                '4' | '@' => 'a',
+                '5' | '$' => 's',
+                '7' => 't',
+                '8' => 'b',
+                _ => c,
+            };
+
+            if letter != c {
+                substitutions += 1;
+            }
+
+            letter
+        })
+        .collect();
+
+    (letters, substitutions)
+}
+
+/// Groups consecutive repeated letters, e.g. `shiiit` becomes `[('s', 1), ('h', 1), ('i', 3), ('t', 1)]`.
+fn letter_runs(letters: &str) -> LetterRuns {
+    let mut runs: LetterRuns = Vec::new();
+
+    for letter in letters.chars() {
+        match runs.last_mut() {
+            Some((last, count)) if *last == letter => *count += 1,
+            _ => runs.push((letter, 1)),
+        }
+    }
+
+    runs
+}
+
+#[cfg(test)]
+mod word_list_tests {
+    use super::{ProfanityFilter, WordListFilter};
+
+    fn filter() -> WordListFilter {
+        WordListFilter::new(["shit", "ass", "Damn"])
+    }
+
+    #[tokio::test]
+    async fn censor_listed_words() {
+        let res = filter()
+            .check(String::from("Damn, this is shit."))
+            .await
+            .unwrap();
+
+        assert_eq!(res.censored_content, "****, this is ****.");
+        assert_eq!(res.bad_words_total, 2);
+        assert_eq!(res.bad_words_list[0].word, "damn");
+        assert_eq!(res.bad_words_list[0].deviations, 0);
+        assert_eq!(res.bad_words_list[1].start, 14);
+        assert_eq!(res.bad_words_list[1].end, 18);
+    }
+
+    #[tokio::test]
+    async fn censor_leetspeak_and_repeated_letters() {
+        let res = filter()
+            .check(String::from("what a sh1iit, @ss!"))
+            .await
+            .unwrap();
+
+        assert_eq!(res.censored_content, "what a ******, ***!");
+        assert_eq!(res.bad_words_list[0].original, "sh1iit");
+        assert_eq!(res.bad_words_list[0].deviations, 3);
+        assert_eq!(res.bad_words_list[1].original, "@ss");
+        assert_eq!(res.bad_words_list[1].deviations, 1);
+    }
+
+    #[tokio::test]
+    async fn keep_words_with_fewer_letters() {
+        let res = filter()
+            .check(String::from("Rust as a language"))
+            .await
+            .unwrap();
+
+        assert_eq!(res.censored_content, "Rust as a language");
+        assert_eq!(res.bad_words_total, 0);
+    }
+}
@@ -1,20 +1,32 @@
+use chrono::{DateTime, Utc};
 use handle_errors::Error;
+use uuid::Uuid;
 
 use sqlx::{
-    postgres::{PgPool, PgPoolOptions, PgRow},
-    Row,
+    postgres::{PgPool, PgPoolOptions, PgRow, Postgres},
+    Row, Transaction,
 };
 
 use crate::{
     crypt::hash_password,
     types::{
-        account::{Account, AccountId, NewAccount},
-        answers::{Answer, AnswerId, NewAnswer},
-        questions::{NewQuestion, Question, QuestionId},
+        account::{
+            validate_password, Account, AccountDetails, AccountId, NewAccount, PasswordHash, Role,
+        },
+        answers::{Answer, AnswerId, NewAnswer, UpdateAnswer},
+        flags::{Flag, FlagId, FlagReason, FlagStatus, FlagTarget, NewFlag},
+        moderation::ModerationStatus,
+        pagination::Cursor,
+        questions::{NewQuestion, Question, QuestionId, QuestionSort},
+        search::{highlight_snippet, snippet_options, SearchResult, SearchResultKind},
+        tags::{Tag, TagFilter, TagMatch, UpdateTag},
+        votes::{VoteDirection, VoteTarget},
     },
 };
 
 const DB_MAX_CONNECTIONS: u32 = 5;
+/// SQLSTATE of the foreign key violations
+const FOREIGN_KEY_VIOLATION: &str = "23503";
 
 #[derive(Clone, Debug)]
 pub struct Store {
@@ -37,19 +49,44 @@ impl Store {
         }
     }
 
+    /// Returns a page of questions.
+    ///
+    /// When `after` is set the page starts after the cursor position (keyset pagination) and
+    /// `offset` is not used. Cursors are only supported by `QuestionSort::Newest` and `QuestionSort::Oldest`.
     pub async fn get_questions(
         &self,
         limit: Option<u32>,
         offset: u32,
+        sort: QuestionSort,
+        tag_filter: Option<TagFilter>,
+        after: Option<Cursor>,
     ) -> Result<Vec<Question>, Error> {
-        let query_result = sqlx::query("SELECT * FROM questions LIMIT $1 OFFSET $2")
-            .bind(limit)
-            .bind(offset)
+        let query = format!(
+            "SELECT * FROM questions {} ORDER BY {} LIMIT $1 OFFSET $2",
+            questions_where_clause(sort, tag_filter.as_ref(), after.as_ref(), 3),
+            question_sort_clauses(sort).1
+        );
+        let mut query = sqlx::query(&query)
+            .bind(limit.map(i64::from))
+            .bind(i64::from(offset));
+
+        if let Some(tag_filter) = tag_filter {
+            query = query.bind(tag_filter.tags);
+        }
+
+        if let Some(Cursor::After { created_on, id }) = after {
+            query = query.bind(created_on).bind(id);
+        }
+
+        let query_result = query
             .map(|row: PgRow| Question {
                 id: QuestionId(row.get("id")),
                 title: row.get("title"),
                 content: row.get("content"),
                 tags: row.get("tags"),
+                accepted_answer: row.get::<Option<i32>, _>("accepted_answer").map(AnswerId),
+                score: row.get("score"),
+                created_on: row.get("created_on"),
             })
             .fetch_all(&self.connection)
             .await;
@@ -64,28 +101,86 @@ impl Store {
         }
     }
 
+    /// Returns the number of questions matching the sort order and the tag filter.
+    pub async fn count_questions(
+        &self,
+        sort: QuestionSort,
+        tag_filter: Option<TagFilter>,
+    ) -> Result<i64, Error> {
+        let query = format!(
+            "SELECT COUNT(*) AS total FROM questions {}",
+            questions_where_clause(sort, tag_filter.as_ref(), None, 1)
+        );
+        let mut query = sqlx::query(&query);
+
+        if let Some(tag_filter) = tag_filter {
+            query = query.bind(tag_filter.tags);
+        }
+
+        let query_result = query
+            .map(|row: PgRow| row.get("total"))
+            .fetch_one(&self.connection)
+            .await;
+
+        match query_result {
+            Ok(total) => Ok(total),
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "Count Questions Error: {:?}", err);
+
+                Err(Error::DatabaseQueryError(err))
+            }
+        }
+    }
+
     pub async fn add_question(&self, new_question: NewQuestion) -> Result<Question, Error> {
+        let mut tx = self.connection.begin().await.map_err(|err| {
+            tracing::event!(tracing::Level::ERROR, "{:?}", err);
+
+            Error::DatabaseQueryError(err)
+        })?;
+
+        if let Some(tags) = &new_question.tags {
+            if let Err(err) = add_tags(&mut tx, tags).await {
+                tracing::event!(tracing::Level::ERROR, "Add Tags Error: {:?}", err);
+
+                return Err(Error::DatabaseQueryError(err));
+            }
+        }
+
         let query_result = sqlx::query(
             "
-            INSERT INTO questions (title, content, tags, account_id) 
-            VALUES ($1, $2, $3, $4) RETURNING id, title, content, tags;
+            INSERT INTO questions (title, content, tags, account_id, moderation_status) 
+            VALUES ($1, $2, $3, $4, $5) RETURNING id, title, content, tags, accepted_answer, score, created_on;
             ",
         )
         .bind(new_question.title)
         .bind(new_question.content)
         .bind(new_question.tags)
         .bind(new_question.account_id.0)
+        .bind(new_question.moderation_status.as_str())
         .map(|row: PgRow| Question {
             id: QuestionId(row.get("id")),
             title: row.get("title"),
             content: row.get("content"),
             tags: row.get("tags"),
+            accepted_answer: row.get::<Option<i32>, _>("accepted_answer").map(AnswerId),
+            score: row.get("score"),
+            created_on: row.get("created_on"),
         })
-        .fetch_one(&self.connection)
+        .fetch_one(&mut tx)
         .await;
 
-        match query_result {
-            Ok(question) => Ok(question),
+        let question = match query_result {
+            Ok(question) => question,
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", err);
+
+                return Err(Error::DatabaseQueryError(err));
+            }
+        };
+
+        match tx.commit().await {
+            Ok(_) => Ok(question),
             Err(err) => {
                 tracing::event!(tracing::Level::ERROR, "{:?}", err);
 
@@ -95,16 +190,20 @@ impl Store {
     }
 
     pub async fn get_question(&self, question_id: i32) -> Result<Question, Error> {
-        let query_result = sqlx::query("SELECT * FROM questions WHERE id = $1;")
-            .bind(question_id)
-            .map(|row: PgRow| Question {
-                id: QuestionId(row.get("id")),
-                title: row.get("title"),
-                content: row.get("content"),
-                tags: row.get("tags"),
-            })
-            .fetch_one(&self.connection)
-            .await;
+        let query_result =
+            sqlx::query("SELECT * FROM questions WHERE id = $1 AND moderation_status <> 'held';")
+                .bind(question_id)
+                .map(|row: PgRow| Question {
+                    id: QuestionId(row.get("id")),
+                    title: row.get("title"),
+                    content: row.get("content"),
+                    tags: row.get("tags"),
+                    accepted_answer: row.get::<Option<i32>, _>("accepted_answer").map(AnswerId),
+                    score: row.get("score"),
+                    created_on: row.get("created_on"),
+                })
+                .fetch_one(&self.connection)
+                .await;
 
         match query_result {
             Ok(question) => Ok(question),
@@ -120,33 +219,61 @@ impl Store {
         &self,
         question: Question,
         question_id: i32,
+        moderation_status: ModerationStatus,
     ) -> Result<Question, Error> {
+        let mut tx = self.connection.begin().await.map_err(|err| {
+            tracing::event!(tracing::Level::ERROR, "{:?}", err);
+
+            Error::DatabaseQueryError(err)
+        })?;
+
+        if let Some(tags) = &question.tags {
+            if let Err(err) = add_tags(&mut tx, tags).await {
+                tracing::event!(tracing::Level::ERROR, "Add Tags Error: {:?}", err);
+
+                return Err(Error::DatabaseQueryError(err));
+            }
+        }
+
         let query_result = sqlx::query(
             "
             UPDATE questions 
-            SET title = $1, content = $2, tags = $3 
-            WHERE id = $4
-            RETURNING id, title, content, tags;
+            SET title = $1, content = $2, tags = $3, moderation_status = $4 
+            WHERE id = $5
+            RETURNING id, title, content, tags, accepted_answer, score, created_on;
             ",
         )
         .bind(question.title)
         .bind(question.content)
         .bind(question.tags)
+        .bind(moderation_status.as_str())
         .bind(question_id)
         .map(|row: PgRow| Question {
             id: QuestionId(row.get("id")),
             title: row.get("title"),
             content: row.get("content"),
             tags: row.get("tags"),
+            accepted_answer: row.get::<Option<i32>, _>("accepted_answer").map(AnswerId),
+            score: row.get("score"),
+            created_on: row.get("created_on"),
         })
-        .fetch_one(&self.connection)
+        .fetch_one(&mut tx)
         .await;
 
-        match query_result {
-            Ok(question) => Ok(question),
+        let question = match query_result {
+            Ok(question) => question,
             Err(err) => {
                 tracing::event!(tracing::Level::ERROR, "Update Question Error: {:?}", err);
 
+                return Err(Error::DatabaseQueryError(err));
+            }
+        };
+
+        match tx.commit().await {
+            Ok(_) => Ok(question),
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", err);
+
                 Err(Error::DatabaseQueryError(err))
             }
         }
@@ -168,20 +295,174 @@ impl Store {
         }
     }
 
+    /// Marks an answer as the accepted one of a question.
+    ///
+    /// The update only happens when the answer belongs to the question, otherwise it returns
+    /// `Error::AnswerNotFound`.
+    pub async fn accept_answer(&self, question_id: i32, answer_id: i32) -> Result<Question, Error> {
+        let query_result = sqlx::query(
+            "
+            UPDATE questions 
+            SET accepted_answer = $1 
+            WHERE id = $2 
+            AND EXISTS (SELECT 1 FROM answers WHERE id = $1 AND corresponding_question = $2)
+            RETURNING id, title, content, tags, accepted_answer, score, created_on;
+            ",
+        )
+        .bind(answer_id)
+        .bind(question_id)
+        .map(|row: PgRow| Question {
+            id: QuestionId(row.get("id")),
+            title: row.get("title"),
+            content: row.get("content"),
+            tags: row.get("tags"),
+            accepted_answer: row.get::<Option<i32>, _>("accepted_answer").map(AnswerId),
+            score: row.get("score"),
+            created_on: row.get("created_on"),
+        })
+        .fetch_optional(&self.connection)
+        .await;
+
+        match query_result {
+            Ok(Some(question)) => Ok(question),
+            Ok(None) => Err(Error::AnswerNotFound),
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "Accept Answer Error: {:?}", err);
+
+                Err(Error::DatabaseQueryError(err))
+            }
+        }
+    }
+
+    pub async fn unaccept_answer(&self, question_id: i32) -> Result<Question, Error> {
+        let query_result = sqlx::query(
+            "
+            UPDATE questions 
+            SET accepted_answer = NULL 
+            WHERE id = $1
+            RETURNING id, title, content, tags, accepted_answer, score, created_on;
+            ",
+        )
+        .bind(question_id)
+        .map(|row: PgRow| Question {
+            id: QuestionId(row.get("id")),
+            title: row.get("title"),
+            content: row.get("content"),
+            tags: row.get("tags"),
+            accepted_answer: row.get::<Option<i32>, _>("accepted_answer").map(AnswerId),
+            score: row.get("score"),
+            created_on: row.get("created_on"),
+        })
+        .fetch_one(&self.connection)
+        .await;
+
+        match query_result {
+            Ok(question) => Ok(question),
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "Unaccept Answer Error: {:?}", err);
+
+                Err(Error::DatabaseQueryError(err))
+            }
+        }
+    }
+
+    /// Returns the tags of the catalog, the most used ones first.
+    pub async fn get_tags(&self, limit: Option<u32>, offset: u32) -> Result<Vec<Tag>, Error> {
+        let query_result = sqlx::query(
+            "
+            SELECT tags.name, tags.description, COUNT(questions.id) AS question_count
+            FROM tags
+            LEFT JOIN questions ON questions.tags @> ARRAY[tags.name::TEXT]
+            GROUP BY tags.id
+            ORDER BY question_count DESC, tags.name
+            LIMIT $1 OFFSET $2;
+            ",
+        )
+        .bind(limit.map(i64::from))
+        .bind(i64::from(offset))
+        .map(|row: PgRow| Tag {
+            name: row.get("name"),
+            description: row.get("description"),
+            question_count: row.get("question_count"),
+        })
+        .fetch_all(&self.connection)
+        .await;
+
+        match query_result {
+            Ok(tags) => Ok(tags),
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "Get Tags Error: {:?}", err);
+
+                Err(Error::DatabaseQueryError(err))
+            }
+        }
+    }
+
+    pub async fn count_tags(&self) -> Result<i64, Error> {
+        let query_result = sqlx::query("SELECT COUNT(*) AS total FROM tags;")
+            .map(|row: PgRow| row.get("total"))
+            .fetch_one(&self.connection)
+            .await;
+
+        match query_result {
+            Ok(total) => Ok(total),
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "Count Tags Error: {:?}", err);
+
+                Err(Error::DatabaseQueryError(err))
+            }
+        }
+    }
+
+    pub async fn update_tag(&self, tag: UpdateTag, name: String) -> Result<Tag, Error> {
+        let query_result = sqlx::query(
+            "
+            UPDATE tags 
+            SET description = $1 
+            WHERE name = $2
+            RETURNING name, description, 
+            (SELECT COUNT(*) FROM questions WHERE questions.tags @> ARRAY[tags.name::TEXT]) AS question_count;
+            ",
+        )
+        .bind(tag.description)
+        .bind(name)
+        .map(|row: PgRow| Tag {
+            name: row.get("name"),
+            description: row.get("description"),
+            question_count: row.get("question_count"),
+        })
+        .fetch_optional(&self.connection)
+        .await;
+
+        match query_result {
+            Ok(Some(tag)) => Ok(tag),
+            Ok(None) => Err(Error::TagNotFound),
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "Update Tag Error: {:?}", err);
+
+                Err(Error::DatabaseQueryError(err))
+            }
+        }
+    }
+
     pub async fn add_answer(&self, new_answer: NewAnswer) -> Result<Answer, Error> {
         let query_result = sqlx::query(
             "
-            INSERT INTO answers (content, corresponding_question, account_id) 
-            VALUES ($1, $2, $3) RETURNING id, content, corresponding_question;
+            INSERT INTO answers (content, corresponding_question, account_id, moderation_status) 
+            VALUES ($1, $2, $3, $4) RETURNING id, content, corresponding_question, account_id, created_on, score;
             ",
         )
         .bind(new_answer.content)
         .bind(new_answer.question_id.0)
         .bind(new_answer.account_id.0)
+        .bind(new_answer.moderation_status.as_str())
         .map(|row: PgRow| Answer {
             id: AnswerId(row.get("id")),
             content: row.get("content"),
             question_id: QuestionId(row.get("corresponding_question")),
+            account_id: AccountId(row.get("account_id")),
+            created_on: row.get("created_on"),
+            score: row.get("score"),
         })
         .fetch_one(&self.connection)
         .await;
@@ -196,47 +477,109 @@ impl Store {
         }
     }
 
-    pub async fn add_account(&self, new_account: NewAccount) -> Result<Account, Error> {
-        let hashed_password = hash_password(new_account.password.as_bytes());
+    pub async fn get_answers(
+        &self,
+        question_id: i32,
+        limit: Option<u32>,
+        offset: u32,
+    ) -> Result<Vec<Answer>, Error> {
         let query_result = sqlx::query(
             "
-            INSERT INTO accounts (email, password) 
-            VALUES ($1, $2) RETURNING id, email, password;
+            SELECT id, content, corresponding_question, account_id, created_on, score
+            FROM answers
+            WHERE corresponding_question = $1 AND moderation_status <> 'held'
+            ORDER BY created_on, id
+            LIMIT $2 OFFSET $3;
             ",
         )
-        .bind(new_account.email)
-        .bind(hashed_password)
-        .map(|row: PgRow| Account {
-            id: AccountId(row.get("id")),
-            email: row.get("email"),
-            password: row.get("password"),
+        .bind(question_id)
+        .bind(limit.map(i64::from))
+        .bind(i64::from(offset))
+        .map(|row: PgRow| Answer {
+            id: AnswerId(row.get("id")),
+            content: row.get("content"),
+            question_id: QuestionId(row.get("corresponding_question")),
+            account_id: AccountId(row.get("account_id")),
+            created_on: row.get("created_on"),
+            score: row.get("score"),
+        })
+        .fetch_all(&self.connection)
+        .await;
+
+        match query_result {
+            Ok(answers) => Ok(answers),
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "Get Answers Error: {:?}", err);
+
+                Err(Error::DatabaseQueryError(err))
+            }
+        }
+    }
+
+    pub async fn count_answers(&self, question_id: i32) -> Result<i64, Error> {
+        let query_result =
+            sqlx::query("SELECT COUNT(*) AS total FROM answers WHERE corresponding_question = $1 AND moderation_status <> 'held';")
+                .bind(question_id)
+                .map(|row: PgRow| row.get("total"))
+                .fetch_one(&self.connection)
+                .await;
+
+        match query_result {
+            Ok(total) => Ok(total),
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "Count Answers Error: {:?}", err);
+
+                Err(Error::DatabaseQueryError(err))
+            }
+        }
+    }
+
+    pub async fn update_answer(
+        &self,
+        answer: UpdateAnswer,
+        answer_id: i32,
+        moderation_status: ModerationStatus,
+    ) -> Result<Answer, Error> {
+        let query_result = sqlx::query(
+            "
+            UPDATE answers 
+            SET content = $1, moderation_status = $2 
+            WHERE id = $3
+            RETURNING id, content, corresponding_question, account_id, created_on, score;
+            ",
+        )
+        .bind(answer.content)
+        .bind(moderation_status.as_str())
+        .bind(answer_id)
+        .map(|row: PgRow| Answer {
+            id: AnswerId(row.get("id")),
+            content: row.get("content"),
+            question_id: QuestionId(row.get("corresponding_question")),
+            account_id: AccountId(row.get("account_id")),
+            created_on: row.get("created_on"),
+            score: row.get("score"),
         })
         .fetch_one(&self.connection)
         .await;
 
         match query_result {
-            Ok(account) => Ok(account),
+            Ok(answer) => Ok(answer),
             Err(err) => {
-                tracing::event!(tracing::Level::ERROR, "{:?}", err);
+                tracing::event!(tracing::Level::ERROR, "Update Answer Error: {:?}", err);
 
                 Err(Error::DatabaseQueryError(err))
             }
         }
     }
 
-    pub async fn get_account(&self, email: String) -> Result<Account, Error> {
-        let query_result = sqlx::query("SELECT * FROM accounts WHERE email = $1;")
-            .bind(email)
-            .map(|row: PgRow| Account {
-                id: AccountId(row.get("id")),
-                email: row.get("email"),
-                password: row.get("password"),
-            })
-            .fetch_one(&self.connection)
+    pub async fn delete_answer(&self, answer_id: i32) -> Result<bool, Error> {
+        let query_result = sqlx::query("DELETE FROM answers WHERE id = $1;")
+            .bind(answer_id)
+            .execute(&self.connection)
             .await;
 
         match query_result {
-            Ok(account) => Ok(account),
+            Ok(_) => Ok(true),
             Err(err) => {
                 tracing::event!(tracing::Level::ERROR, "{:?}", err);
 
@@ -245,20 +588,70 @@ impl Store {
         }
     }
 
-    pub async fn is_question_owner(
+    /// Stores the vote of an account and updates the score of the voted post.
+    ///
+    /// An account can only vote once per post, a second vote returns `Error::AlreadyVoted`.
+    pub async fn add_vote(
         &self,
-        question_id: i32,
+        target: VoteTarget,
+        direction: VoteDirection,
         account_id: i32,
     ) -> Result<bool, Error> {
-        let query_result =
-            sqlx::query("SELECT * FROM questions WHERE id = $1 AND account_id = $2;")
-                .bind(question_id)
-                .bind(account_id)
-                .fetch_optional(&self.connection)
-                .await;
+        let (target_column, target_table, target_id) = match &target {
+            VoteTarget::Question(question_id) => ("question_id", "questions", question_id.0),
+            VoteTarget::Answer(answer_id) => ("answer_id", "answers", answer_id.0),
+        };
+        let mut tx = self.connection.begin().await.map_err(|err| {
+            tracing::event!(tracing::Level::ERROR, "{:?}", err);
 
-        match query_result {
-            Ok(question) => Ok(question.is_some()),
+            Error::DatabaseQueryError(err)
+        })?;
+        let insert_query = format!(
+            "
+            INSERT INTO votes (account_id, {}, value) 
+            VALUES ($1, $2, $3) 
+            ON CONFLICT DO NOTHING 
+            RETURNING id;
+            ",
+            target_column
+        );
+        let vote_result = sqlx::query(&insert_query)
+            .bind(account_id)
+            .bind(target_id)
+            .bind(direction.value())
+            .fetch_optional(&mut tx)
+            .await;
+
+        match vote_result {
+            Ok(Some(_)) => (),
+            Ok(None) => return Err(Error::AlreadyVoted),
+            // The post does not exist
+            Err(err) if is_foreign_key_violation(&err) => return Err(target.not_found()),
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "Add Vote Error: {:?}", err);
+
+                return Err(Error::DatabaseQueryError(err));
+            }
+        }
+
+        let update_query = format!(
+            "UPDATE {} SET score = score + $1 WHERE id = $2;",
+            target_table
+        );
+        let score_result = sqlx::query(&update_query)
+            .bind(i32::from(direction.value()))
+            .bind(target_id)
+            .execute(&mut tx)
+            .await;
+
+        if let Err(err) = score_result {
+            tracing::event!(tracing::Level::ERROR, "Update Score Error: {:?}", err);
+
+            return Err(Error::DatabaseQueryError(err));
+        }
+
+        match tx.commit().await {
+            Ok(_) => Ok(true),
             Err(err) => {
                 tracing::event!(tracing::Level::ERROR, "{:?}", err);
 
@@ -266,4 +659,1328 @@ impl Store {
             }
         }
     }
+
+    /// Searches questions and answers using the full-text search columns (`search_vector`).
+    ///
+    /// Question titles weight more than question and answer contents, results are sorted by rank.
+    pub async fn search(
+        &self,
+        query: String,
+        limit: Option<u32>,
+        offset: u32,
+    ) -> Result<Vec<SearchResult>, Error> {
+        let query_result = sqlx::query(
+            "
+            SELECT question_id, answer_id, title, snippet, rank FROM (
+                SELECT questions.id AS question_id, NULL::INTEGER AS answer_id, questions.title,
+                ts_headline('english', questions.content, search_query, $4) AS snippet,
+                ts_rank(questions.search_vector, search_query) AS rank
+                FROM questions, websearch_to_tsquery('english', $1) AS search_query
+                WHERE questions.search_vector @@ search_query AND questions.moderation_status <> 'held'
+                UNION ALL
+                SELECT answers.corresponding_question AS question_id, answers.id AS answer_id, questions.title,
+                ts_headline('english', answers.content, search_query, $4) AS snippet,
+                ts_rank(answers.search_vector, search_query) AS rank
+                FROM answers
+                JOIN questions ON questions.id = answers.corresponding_question,
+                websearch_to_tsquery('english', $1) AS search_query
+                WHERE answers.search_vector @@ search_query AND answers.moderation_status <> 'held'
+            ) AS results
+            ORDER BY rank DESC, question_id DESC, answer_id DESC NULLS FIRST
+            LIMIT $2 OFFSET $3;
+            ",
+        )
+        .bind(query)
+        .bind(limit.map(i64::from))
+        .bind(i64::from(offset))
+        .bind(snippet_options())
+        .map(|row: PgRow| {
+            let answer_id = row.get::<Option<i32>, _>("answer_id").map(AnswerId);
+
+            SearchResult {
+                kind: match answer_id {
+                    Some(_) => SearchResultKind::Answer,
+                    None => SearchResultKind::Question,
+                },
+                question_id: QuestionId(row.get("question_id")),
+                answer_id,
+                title: row.get("title"),
+                snippet: highlight_snippet(row.get("snippet")),
+                rank: row.get("rank"),
+            }
+        })
+        .fetch_all(&self.connection)
+        .await;
+
+        match query_result {
+            Ok(results) => Ok(results),
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "Search Error: {:?}", err);
+
+                Err(Error::DatabaseQueryError(err))
+            }
+        }
+    }
+
+    /// Returns the number of questions and answers matching a search query.
+    pub async fn count_search_results(&self, query: String) -> Result<i64, Error> {
+        let query_result = sqlx::query(
+            "
+            SELECT
+            (SELECT COUNT(*) FROM questions
+            WHERE search_vector @@ websearch_to_tsquery('english', $1) AND moderation_status <> 'held') +
+            (SELECT COUNT(*) FROM answers
+            WHERE search_vector @@ websearch_to_tsquery('english', $1) AND moderation_status <> 'held')
+            AS total;
+            ",
+        )
+        .bind(query)
+        .map(|row: PgRow| row.get("total"))
+        .fetch_one(&self.connection)
+        .await;
+
+        match query_result {
+            Ok(total) => Ok(total),
+            Err(err) => {
+                tracing::event!(
+                    tracing::Level::ERROR,
+                    "Count Search Results Error: {:?}",
+                    err
+                );
+
+                Err(Error::DatabaseQueryError(err))
+            }
+        }
+    }
+
+    /// Returns the questions whose moderation was skipped because the profanity service was unavailable.
+    pub async fn get_questions_to_recheck(&self, limit: u32) -> Result<Vec<Question>, Error> {
+        let query_result = sqlx::query(
+            "
+            SELECT * FROM questions 
+            WHERE moderation_status IN ('pending_recheck', 'held') 
+            ORDER BY id 
+            LIMIT $1;
+            ",
+        )
+        .bind(i64::from(limit))
+        .map(|row: PgRow| Question {
+            id: QuestionId(row.get("id")),
+            title: row.get("title"),
+            content: row.get("content"),
+            tags: row.get("tags"),
+            accepted_answer: row.get::<Option<i32>, _>("accepted_answer").map(AnswerId),
+            score: row.get("score"),
+            created_on: row.get("created_on"),
+        })
+        .fetch_all(&self.connection)
+        .await;
+
+        match query_result {
+            Ok(questions) => Ok(questions),
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", err);
+
+                Err(Error::DatabaseQueryError(err))
+            }
+        }
+    }
+
+    /// Stores the moderated title and content of a question which was pending a re-check.
+    ///
+    /// The question is only updated if it has not been edited since it was read (`question`), so
+    /// an edit is never overwritten with the moderated old content.
+    pub async fn update_question_moderation(
+        &self,
+        question: &Question,
+        title: String,
+        content: String,
+        moderation_status: ModerationStatus,
+    ) -> Result<bool, Error> {
+        let query_result = sqlx::query(
+            "
+            UPDATE questions 
+            SET title = $1, content = $2, moderation_status = $3 
+            WHERE id = $4 AND title = $5 AND content = $6;
+            ",
+        )
+        .bind(title)
+        .bind(content)
+        .bind(moderation_status.as_str())
+        .bind(question.id.0)
+        .bind(&question.title)
+        .bind(&question.content)
+        .execute(&self.connection)
+        .await;
+
+        match query_result {
+            Ok(res) => Ok(res.rows_affected() > 0),
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", err);
+
+                Err(Error::DatabaseQueryError(err))
+            }
+        }
+    }
+
+    /// Returns the answers whose moderation was skipped because the profanity service was unavailable.
+    pub async fn get_answers_to_recheck(&self, limit: u32) -> Result<Vec<Answer>, Error> {
+        let query_result = sqlx::query(
+            "
+            SELECT id, content, corresponding_question, account_id, created_on, score 
+            FROM answers 
+            WHERE moderation_status IN ('pending_recheck', 'held') 
+            ORDER BY id 
+            LIMIT $1;
+            ",
+        )
+        .bind(i64::from(limit))
+        .map(|row: PgRow| Answer {
+            id: AnswerId(row.get("id")),
+            content: row.get("content"),
+            question_id: QuestionId(row.get("corresponding_question")),
+            account_id: AccountId(row.get("account_id")),
+            created_on: row.get("created_on"),
+            score: row.get("score"),
+        })
+        .fetch_all(&self.connection)
+        .await;
+
+        match query_result {
+            Ok(answers) => Ok(answers),
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", err);
+
+                Err(Error::DatabaseQueryError(err))
+            }
+        }
+    }
+
+    /// Stores the moderated content of an answer which was pending a re-check, unless it has been
+    /// edited since it was read (`answer`).
+    pub async fn update_answer_moderation(
+        &self,
+        answer: &Answer,
+        content: String,
+        moderation_status: ModerationStatus,
+    ) -> Result<bool, Error> {
+        let query_result = sqlx::query(
+            "
+            UPDATE answers 
+            SET content = $1, moderation_status = $2 
+            WHERE id = $3 AND content = $4;
+            ",
+        )
+        .bind(content)
+        .bind(moderation_status.as_str())
+        .bind(answer.id.0)
+        .bind(&answer.content)
+        .execute(&self.connection)
+        .await;
+
+        match query_result {
+            Ok(res) => Ok(res.rows_affected() > 0),
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", err);
+
+                Err(Error::DatabaseQueryError(err))
+            }
+        }
+    }
+
+    /// Stores a flag raised on a post. Flags without `account_id` are raised by the moderation.
+    ///
+    /// An account can only flag a post once, a second flag returns `Error::AlreadyFlagged`. Flagging
+    /// a missing post returns `Error::QuestionNotFound` or `Error::AnswerNotFound`.
+    pub async fn add_flag(
+        &self,
+        target: FlagTarget,
+        new_flag: NewFlag,
+        account_id: Option<i32>,
+    ) -> Result<bool, Error> {
+        let (target_column, target_id) = match &target {
+            FlagTarget::Question(question_id) => ("question_id", question_id.0),
+            FlagTarget::Answer(answer_id) => ("answer_id", answer_id.0),
+        };
+        let insert_query = format!(
+            "
+            INSERT INTO flags (account_id, {}, reason, details) 
+            VALUES ($1, $2, $3, $4) 
+            ON CONFLICT DO NOTHING 
+            RETURNING id;
+            ",
+            target_column
+        );
+        let query_result = sqlx::query(&insert_query)
+            .bind(account_id)
+            .bind(target_id)
+            .bind(new_flag.reason.as_str())
+            .bind(new_flag.details)
+            .fetch_optional(&self.connection)
+            .await;
+
+        match query_result {
+            Ok(Some(_)) => Ok(true),
+            Ok(None) => Err(Error::AlreadyFlagged),
+            Err(err) if is_foreign_key_violation(&err) => Err(target.not_found()),
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "Add Flag Error: {:?}", err);
+
+                Err(Error::DatabaseQueryError(err))
+            }
+        }
+    }
+
+    /// Returns the flags with a status together with the content of the flagged posts, oldest first.
+    pub async fn get_flags(
+        &self,
+        status: FlagStatus,
+        limit: Option<u32>,
+        offset: u32,
+    ) -> Result<Vec<Flag>, Error> {
+        let query_result = sqlx::query(
+            "
+            SELECT flags.*, COALESCE(questions.content, answers.content) AS post_content 
+            FROM flags 
+            LEFT JOIN questions ON questions.id = flags.question_id 
+            LEFT JOIN answers ON answers.id = flags.answer_id 
+            WHERE flags.status = $1 
+            ORDER BY flags.created_on, flags.id 
+            LIMIT $2 OFFSET $3;
+            ",
+        )
+        .bind(status.as_str())
+        .bind(limit.map(i64::from))
+        .bind(i64::from(offset))
+        .map(|row: PgRow| Flag {
+            id: FlagId(row.get("id")),
+            reason: FlagReason::from_db(row.get("reason")),
+            details: row.get("details"),
+            status,
+            account_id: row.get::<Option<i32>, _>("account_id").map(AccountId),
+            question_id: row.get::<Option<i32>, _>("question_id").map(QuestionId),
+            answer_id: row.get::<Option<i32>, _>("answer_id").map(AnswerId),
+            content: row.get("post_content"),
+            created_on: row.get("created_on"),
+        })
+        .fetch_all(&self.connection)
+        .await;
+
+        match query_result {
+            Ok(flags) => Ok(flags),
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "Get Flags Error: {:?}", err);
+
+                Err(Error::DatabaseQueryError(err))
+            }
+        }
+    }
+
+    pub async fn count_flags(&self, status: FlagStatus) -> Result<i64, Error> {
+        let query_result = sqlx::query("SELECT COUNT(*) AS total FROM flags WHERE status = $1;")
+            .bind(status.as_str())
+            .map(|row: PgRow| row.get("total"))
+            .fetch_one(&self.connection)
+            .await;
+
+        match query_result {
+            Ok(total) => Ok(total),
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "Count Flags Error: {:?}", err);
+
+                Err(Error::DatabaseQueryError(err))
+            }
+        }
+    }
+
+    /// Returns the post a flag was raised on.
+    pub async fn get_flag_target(&self, flag_id: i32) -> Result<FlagTarget, Error> {
+        let query_result = sqlx::query("SELECT question_id, answer_id FROM flags WHERE id = $1;")
+            .bind(flag_id)
+            .map(
+                // Flags have either a question or an answer (see the flags table constraint)
+                |row: PgRow| match row.get::<Option<i32>, _>("question_id") {
+                    Some(question_id) => FlagTarget::Question(QuestionId(question_id)),
+                    None => FlagTarget::Answer(AnswerId(row.get("answer_id"))),
+                },
+            )
+            .fetch_optional(&self.connection)
+            .await;
+
+        match query_result {
+            Ok(Some(target)) => Ok(target),
+            Ok(None) => Err(Error::FlagNotFound),
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", err);
+
+                Err(Error::DatabaseQueryError(err))
+            }
+        }
+    }
+
+    /// Closes every open flag of a post with the status chosen by a moderator.
+    ///
+    /// A post flagged by the moderation (`ModerationStatus::Flagged`) is approved, since it has
+    /// been reviewed.
+    pub async fn review_flags(
+        &self,
+        target: FlagTarget,
+        status: FlagStatus,
+        moderator_id: i32,
+    ) -> Result<bool, Error> {
+        let (target_column, target_table, target_id) = match target {
+            FlagTarget::Question(question_id) => ("question_id", "questions", question_id.0),
+            FlagTarget::Answer(answer_id) => ("answer_id", "answers", answer_id.0),
+        };
+        let mut tx = self.connection.begin().await.map_err(|err| {
+            tracing::event!(tracing::Level::ERROR, "{:?}", err);
+
+            Error::DatabaseQueryError(err)
+        })?;
+        let flags_query = format!(
+            "
+            UPDATE flags 
+            SET status = $1, reviewed_by = $2, reviewed_on = NOW() 
+            WHERE {} = $3 AND status = 'open';
+            ",
+            target_column
+        );
+        let flags_result = sqlx::query(&flags_query)
+            .bind(status.as_str())
+            .bind(moderator_id)
+            .bind(target_id)
+            .execute(&mut tx)
+            .await;
+
+        if let Err(err) = flags_result {
+            tracing::event!(tracing::Level::ERROR, "Review Flags Error: {:?}", err);
+
+            return Err(Error::DatabaseQueryError(err));
+        }
+
+        let post_query = format!(
+            "UPDATE {} SET moderation_status = $1 WHERE id = $2 AND moderation_status = $3;",
+            target_table
+        );
+        let post_result = sqlx::query(&post_query)
+            .bind(ModerationStatus::Approved.as_str())
+            .bind(target_id)
+            .bind(ModerationStatus::Flagged.as_str())
+            .execute(&mut tx)
+            .await;
+
+        if let Err(err) = post_result {
+            tracing::event!(tracing::Level::ERROR, "Approve Post Error: {:?}", err);
+
+            return Err(Error::DatabaseQueryError(err));
+        }
+
+        match tx.commit().await {
+            Ok(_) => Ok(true),
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", err);
+
+                Err(Error::DatabaseQueryError(err))
+            }
+        }
+    }
+
+    pub async fn add_account(&self, new_account: NewAccount) -> Result<Account, Error> {
+        let hashed_password = hash_password(new_account.password.as_bytes());
+        let query_result = sqlx::query(
+            "
+            INSERT INTO accounts (email, password) 
+            VALUES ($1, $2) 
+            RETURNING id, email, password, role, suspended_until, banned_on, login_locked_until;
+            ",
+        )
+        .bind(new_account.email)
+        .bind(hashed_password)
+        .map(|row: PgRow| Account {
+            id: AccountId(row.get("id")),
+            email: row.get("email"),
+            password: PasswordHash::new(row.get("password")),
+            role: Role::from_db(row.get("role")),
+            suspended_until: row.get("suspended_until"),
+            banned_on: row.get("banned_on"),
+            login_locked_until: row.get("login_locked_until"),
+        })
+        .fetch_one(&self.connection)
+        .await;
+
+        match query_result {
+            Ok(account) => Ok(account),
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", err);
+
+                Err(Error::DatabaseQueryError(err))
+            }
+        }
+    }
+
+    /// Returns the account of an email, `None` if there is no account with that email.
+    pub async fn get_account(&self, email: String) -> Result<Option<Account>, Error> {
+        let query_result = sqlx::query("SELECT * FROM accounts WHERE email = $1;")
+            .bind(email)
+            .map(|row: PgRow| Account {
+                id: AccountId(row.get("id")),
+                email: row.get("email"),
+                password: PasswordHash::new(row.get("password")),
+                role: Role::from_db(row.get("role")),
+                suspended_until: row.get("suspended_until"),
+                banned_on: row.get("banned_on"),
+                login_locked_until: row.get("login_locked_until"),
+            })
+            .fetch_optional(&self.connection)
+            .await;
+
+        match query_result {
+            Ok(account) => Ok(account),
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", err);
+
+                Err(Error::DatabaseQueryError(err))
+            }
+        }
+    }
+
+    /// Counts a failed login of an account and returns its consecutive failed logins.
+    pub async fn record_failed_login(&self, account_id: i32) -> Result<i32, Error> {
+        let query_result = sqlx::query(
+            "
+            UPDATE accounts 
+            SET failed_login_attempts = failed_login_attempts + 1 
+            WHERE id = $1 
+            RETURNING failed_login_attempts;
+            ",
+        )
+        .bind(account_id)
+        .map(|row: PgRow| row.get("failed_login_attempts"))
+        .fetch_one(&self.connection)
+        .await;
+
+        match query_result {
+            Ok(failed_attempts) => Ok(failed_attempts),
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", err);
+
+                Err(Error::DatabaseQueryError(err))
+            }
+        }
+    }
+
+    /// Rejects the logins of an account until a date.
+    pub async fn lock_login(&self, account_id: i32, until: DateTime<Utc>) -> Result<bool, Error> {
+        let query_result =
+            sqlx::query("UPDATE accounts SET login_locked_until = $1 WHERE id = $2;")
+                .bind(until)
+                .bind(account_id)
+                .execute(&self.connection)
+                .await;
+
+        match query_result {
+            Ok(_) => Ok(true),
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", err);
+
+                Err(Error::DatabaseQueryError(err))
+            }
+        }
+    }
+
+    /// Forgets the failed logins of an account after a successful login.
+    pub async fn reset_failed_logins(&self, account_id: i32) -> Result<bool, Error> {
+        let query_result = sqlx::query(
+            "
+            UPDATE accounts 
+            SET failed_login_attempts = 0, login_locked_until = NULL 
+            WHERE id = $1 AND (failed_login_attempts > 0 OR login_locked_until IS NOT NULL);
+            ",
+        )
+        .bind(account_id)
+        .execute(&self.connection)
+        .await;
+
+        match query_result {
+            Ok(_) => Ok(true),
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", err);
+
+                Err(Error::DatabaseQueryError(err))
+            }
+        }
+    }
+
+    /// Changes the role of an account. Authenticated requests read the role from the database, so
+    /// the change applies to the existing tokens too.
+    pub async fn update_account_role(&self, account_id: i32, role: Role) -> Result<bool, Error> {
+        let query_result = sqlx::query("UPDATE accounts SET role = $1 WHERE id = $2;")
+            .bind(role.as_str())
+            .bind(account_id)
+            .execute(&self.connection)
+            .await;
+
+        match query_result {
+            Ok(res) if res.rows_affected() > 0 => Ok(true),
+            Ok(_) => Err(Error::AccountNotFound),
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", err);
+
+                Err(Error::DatabaseQueryError(err))
+            }
+        }
+    }
+
+    /// Returns the accounts whose email contains `email` (case insensitive), ordered by id.
+    pub async fn get_accounts(
+        &self,
+        email: Option<String>,
+        limit: Option<u32>,
+        offset: u32,
+    ) -> Result<Vec<AccountDetails>, Error> {
+        let query_result = sqlx::query(
+            "
+            SELECT id, email, role, suspended_until, banned_on, email_verified_on 
+            FROM accounts 
+            WHERE $1::TEXT IS NULL OR email ILIKE '%' || $1 || '%' 
+            ORDER BY id 
+            LIMIT $2 OFFSET $3;
+            ",
+        )
+        .bind(email.as_deref().map(escape_like))
+        .bind(limit.map(i64::from))
+        .bind(i64::from(offset))
+        .map(|row: PgRow| AccountDetails {
+            id: AccountId(row.get("id")),
+            email: row.get("email"),
+            role: Role::from_db(row.get("role")),
+            suspended_until: row.get("suspended_until"),
+            banned_on: row.get("banned_on"),
+            email_verified_on: row.get("email_verified_on"),
+        })
+        .fetch_all(&self.connection)
+        .await;
+
+        match query_result {
+            Ok(accounts) => Ok(accounts),
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "Get Accounts Error: {:?}", err);
+
+                Err(Error::DatabaseQueryError(err))
+            }
+        }
+    }
+
+    pub async fn count_accounts(&self, email: Option<String>) -> Result<i64, Error> {
+        let query_result = sqlx::query(
+            "
+            SELECT COUNT(*) AS total FROM accounts 
+            WHERE $1::TEXT IS NULL OR email ILIKE '%' || $1 || '%';
+            ",
+        )
+        .bind(email.as_deref().map(escape_like))
+        .map(|row: PgRow| row.get("total"))
+        .fetch_one(&self.connection)
+        .await;
+
+        match query_result {
+            Ok(total) => Ok(total),
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "Count Accounts Error: {:?}", err);
+
+                Err(Error::DatabaseQueryError(err))
+            }
+        }
+    }
+
+    pub async fn get_account_details(&self, account_id: i32) -> Result<AccountDetails, Error> {
+        let query_result = sqlx::query(
+            "
+            SELECT id, email, role, suspended_until, banned_on, email_verified_on 
+            FROM accounts 
+            WHERE id = $1;
+            ",
+        )
+        .bind(account_id)
+        .map(|row: PgRow| AccountDetails {
+            id: AccountId(row.get("id")),
+            email: row.get("email"),
+            role: Role::from_db(row.get("role")),
+            suspended_until: row.get("suspended_until"),
+            banned_on: row.get("banned_on"),
+            email_verified_on: row.get("email_verified_on"),
+        })
+        .fetch_optional(&self.connection)
+        .await;
+
+        match query_result {
+            Ok(Some(account)) => Ok(account),
+            Ok(None) => Err(Error::AccountNotFound),
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", err);
+
+                Err(Error::DatabaseQueryError(err))
+            }
+        }
+    }
+
+    /// Suspends an account until a date, `None` lifts the suspension.
+    pub async fn suspend_account(
+        &self,
+        account_id: i32,
+        until: Option<DateTime<Utc>>,
+    ) -> Result<bool, Error> {
+        let query_result = sqlx::query("UPDATE accounts SET suspended_until = $1 WHERE id = $2;")
+            .bind(until)
+            .bind(account_id)
+            .execute(&self.connection)
+            .await;
+
+        match query_result {
+            Ok(res) if res.rows_affected() > 0 => Ok(true),
+            Ok(_) => Err(Error::AccountNotFound),
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", err);
+
+                Err(Error::DatabaseQueryError(err))
+            }
+        }
+    }
+
+    /// Bans an account permanently, or lifts the ban when `banned` is false.
+    pub async fn ban_account(&self, account_id: i32, banned: bool) -> Result<bool, Error> {
+        let query_result = sqlx::query(
+            "
+            UPDATE accounts 
+            SET banned_on = CASE WHEN $1 THEN COALESCE(banned_on, NOW()) END 
+            WHERE id = $2;
+            ",
+        )
+        .bind(banned)
+        .bind(account_id)
+        .execute(&self.connection)
+        .await;
+
+        match query_result {
+            Ok(res) if res.rows_affected() > 0 => Ok(true),
+            Ok(_) => Err(Error::AccountNotFound),
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", err);
+
+                Err(Error::DatabaseQueryError(err))
+            }
+        }
+    }
+
+    /// Deletes an account after moving its questions and answers to the account `reassign_to`.
+    ///
+    /// Votes and flags of the account are kept, so the scores of the posts do not change.
+    pub async fn delete_account(&self, account_id: i32, reassign_to: i32) -> Result<bool, Error> {
+        let mut tx = self.connection.begin().await.map_err(|err| {
+            tracing::event!(tracing::Level::ERROR, "{:?}", err);
+
+            Error::DatabaseQueryError(err)
+        })?;
+
+        for table in ["questions", "answers"] {
+            let reassign_query = format!(
+                "UPDATE {} SET account_id = $1 WHERE account_id = $2;",
+                table
+            );
+            let reassign_result = sqlx::query(&reassign_query)
+                .bind(reassign_to)
+                .bind(account_id)
+                .execute(&mut tx)
+                .await;
+
+            if let Err(err) = reassign_result {
+                tracing::event!(tracing::Level::ERROR, "Reassign Posts Error: {:?}", err);
+
+                return Err(Error::DatabaseQueryError(err));
+            }
+        }
+
+        let delete_result = sqlx::query("DELETE FROM accounts WHERE id = $1;")
+            .bind(account_id)
+            .execute(&mut tx)
+            .await;
+
+        match delete_result {
+            Ok(res) if res.rows_affected() == 0 => return Err(Error::AccountNotFound),
+            Ok(_) => (),
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "Delete Account Error: {:?}", err);
+
+                return Err(Error::DatabaseQueryError(err));
+            }
+        }
+
+        match tx.commit().await {
+            Ok(_) => Ok(true),
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", err);
+
+                Err(Error::DatabaseQueryError(err))
+            }
+        }
+    }
+
+    /// Creates a login session for an account together with its first refresh token.
+    pub async fn add_session(
+        &self,
+        account_id: i32,
+        refresh_token_hash: String,
+        expires_on: DateTime<Utc>,
+    ) -> Result<Uuid, Error> {
+        let session_id = Uuid::new_v4();
+        let mut tx = self.connection.begin().await.map_err(|err| {
+            tracing::event!(tracing::Level::ERROR, "{:?}", err);
+
+            Error::DatabaseQueryError(err)
+        })?;
+        let session_result = sqlx::query("INSERT INTO sessions (id, account_id) VALUES ($1, $2);")
+            .bind(session_id)
+            .bind(account_id)
+            .execute(&mut tx)
+            .await;
+
+        if let Err(err) = session_result {
+            tracing::event!(tracing::Level::ERROR, "Add Session Error: {:?}", err);
+
+            return Err(Error::DatabaseQueryError(err));
+        }
+
+        let token_result = sqlx::query(
+            "
+            INSERT INTO refresh_tokens (session_id, token_hash, expires_on) 
+            VALUES ($1, $2, $3);
+            ",
+        )
+        .bind(session_id)
+        .bind(refresh_token_hash)
+        .bind(expires_on)
+        .execute(&mut tx)
+        .await;
+
+        if let Err(err) = token_result {
+            tracing::event!(tracing::Level::ERROR, "Add Refresh Token Error: {:?}", err);
+
+            return Err(Error::DatabaseQueryError(err));
+        }
+
+        match tx.commit().await {
+            Ok(_) => Ok(session_id),
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", err);
+
+                Err(Error::DatabaseQueryError(err))
+            }
+        }
+    }
+
+    /// Replaces a refresh token by a new one and returns the session and the account it belongs to.
+    ///
+    /// A refresh token can only be used once. Using it again means it has been stolen, so the whole
+    /// session is revoked. Unknown, expired, reused and revoked tokens return `Error::TokenError`.
+    pub async fn rotate_refresh_token(
+        &self,
+        refresh_token_hash: String,
+        new_refresh_token_hash: String,
+        expires_on: DateTime<Utc>,
+    ) -> Result<(Uuid, i32), Error> {
+        let mut tx = self.connection.begin().await.map_err(|err| {
+            tracing::event!(tracing::Level::ERROR, "{:?}", err);
+
+            Error::DatabaseQueryError(err)
+        })?;
+        let used_result = sqlx::query(
+            "
+            UPDATE refresh_tokens 
+            SET used_on = NOW() 
+            WHERE token_hash = $1 AND used_on IS NULL 
+            RETURNING session_id, expires_on > NOW() AS valid;
+            ",
+        )
+        .bind(&refresh_token_hash)
+        .map(|row: PgRow| {
+            (
+                row.get::<Uuid, _>("session_id"),
+                row.get::<bool, _>("valid"),
+            )
+        })
+        .fetch_optional(&mut tx)
+        .await;
+
+        let session_id = match used_result {
+            Ok(Some((session_id, true))) => session_id,
+            Ok(Some((_, false))) => return Err(Error::TokenError),
+            Ok(None) => {
+                drop(tx);
+
+                return self.revoke_reused_refresh_token(refresh_token_hash).await;
+            }
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "Use Refresh Token Error: {:?}", err);
+
+                return Err(Error::DatabaseQueryError(err));
+            }
+        };
+
+        let session_result =
+            sqlx::query("SELECT account_id FROM sessions WHERE id = $1 AND revoked_on IS NULL;")
+                .bind(session_id)
+                .map(|row: PgRow| row.get::<i32, _>("account_id"))
+                .fetch_optional(&mut tx)
+                .await;
+
+        let account_id = match session_result {
+            Ok(Some(account_id)) => account_id,
+            Ok(None) => return Err(Error::TokenError),
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", err);
+
+                return Err(Error::DatabaseQueryError(err));
+            }
+        };
+
+        let token_result = sqlx::query(
+            "
+            INSERT INTO refresh_tokens (session_id, token_hash, expires_on) 
+            VALUES ($1, $2, $3);
+            ",
+        )
+        .bind(session_id)
+        .bind(new_refresh_token_hash)
+        .bind(expires_on)
+        .execute(&mut tx)
+        .await;
+
+        if let Err(err) = token_result {
+            tracing::event!(tracing::Level::ERROR, "Add Refresh Token Error: {:?}", err);
+
+            return Err(Error::DatabaseQueryError(err));
+        }
+
+        match tx.commit().await {
+            Ok(_) => Ok((session_id, account_id)),
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", err);
+
+                Err(Error::DatabaseQueryError(err))
+            }
+        }
+    }
+
+    /// Revokes the session of a refresh token which has already been used. Always returns
+    /// `Error::TokenError`.
+    async fn revoke_reused_refresh_token(
+        &self,
+        refresh_token_hash: String,
+    ) -> Result<(Uuid, i32), Error> {
+        let query_result = sqlx::query(
+            "
+            UPDATE sessions 
+            SET revoked_on = COALESCE(revoked_on, NOW()) 
+            WHERE id = (SELECT session_id FROM refresh_tokens WHERE token_hash = $1) 
+            RETURNING id;
+            ",
+        )
+        .bind(refresh_token_hash)
+        .fetch_optional(&self.connection)
+        .await;
+
+        match query_result {
+            Ok(Some(_)) => {
+                tracing::event!(
+                    tracing::Level::WARN,
+                    "Refresh token reused, the session has been revoked"
+                );
+
+                Err(Error::TokenError)
+            }
+            Ok(None) => Err(Error::TokenError),
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", err);
+
+                Err(Error::DatabaseQueryError(err))
+            }
+        }
+    }
+
+    pub async fn revoke_session(&self, session_id: Uuid) -> Result<bool, Error> {
+        let query_result = sqlx::query(
+            "UPDATE sessions SET revoked_on = COALESCE(revoked_on, NOW()) WHERE id = $1;",
+        )
+        .bind(session_id)
+        .execute(&self.connection)
+        .await;
+
+        match query_result {
+            Ok(_) => Ok(true),
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", err);
+
+                Err(Error::DatabaseQueryError(err))
+            }
+        }
+    }
+
+    pub async fn is_session_active(&self, session_id: Uuid) -> Result<bool, Error> {
+        let query_result =
+            sqlx::query("SELECT id FROM sessions WHERE id = $1 AND revoked_on IS NULL;")
+                .bind(session_id)
+                .fetch_optional(&self.connection)
+                .await;
+
+        match query_result {
+            Ok(session) => Ok(session.is_some()),
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", err);
+
+                Err(Error::DatabaseQueryError(err))
+            }
+        }
+    }
+
+    /// Marks the email of an account as verified. Returns false if the account does not exist or its
+    /// email is not `email` anymore.
+    pub async fn verify_email(&self, account_id: i32, email: String) -> Result<bool, Error> {
+        let query_result = sqlx::query(
+            "
+            UPDATE accounts 
+            SET email_verified_on = COALESCE(email_verified_on, NOW()) 
+            WHERE id = $1 AND email = $2;
+            ",
+        )
+        .bind(account_id)
+        .bind(email)
+        .execute(&self.connection)
+        .await;
+
+        match query_result {
+            Ok(result) => Ok(result.rows_affected() > 0),
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", err);
+
+                Err(Error::DatabaseQueryError(err))
+            }
+        }
+    }
+
+    /// Returns the id of the account of an email, if there is one.
+    pub async fn find_account_id(&self, email: String) -> Result<Option<i32>, Error> {
+        let query_result = sqlx::query("SELECT id FROM accounts WHERE email = $1;")
+            .bind(email)
+            .map(|row: PgRow| row.get("id"))
+            .fetch_optional(&self.connection)
+            .await;
+
+        match query_result {
+            Ok(account_id) => Ok(account_id),
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", err);
+
+                Err(Error::DatabaseQueryError(err))
+            }
+        }
+    }
+
+    /// Changes the password of an account and revokes all its sessions but `keep_session`.
+    pub async fn update_account_password(
+        &self,
+        account_id: i32,
+        password: String,
+        keep_session: Option<Uuid>,
+    ) -> Result<bool, Error> {
+        let mut tx = self.connection.begin().await.map_err(|err| {
+            tracing::event!(tracing::Level::ERROR, "{:?}", err);
+
+            Error::DatabaseQueryError(err)
+        })?;
+
+        if let Err(err) = set_password(&mut tx, account_id, password, keep_session).await {
+            tracing::event!(tracing::Level::ERROR, "Update Password Error: {:?}", err);
+
+            return Err(Error::DatabaseQueryError(err));
+        }
+
+        match tx.commit().await {
+            Ok(_) => Ok(true),
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", err);
+
+                Err(Error::DatabaseQueryError(err))
+            }
+        }
+    }
+
+    pub async fn add_password_reset_token(
+        &self,
+        account_id: i32,
+        token_hash: String,
+        expires_on: DateTime<Utc>,
+    ) -> Result<bool, Error> {
+        let query_result = sqlx::query(
+            "
+            INSERT INTO password_reset_tokens (account_id, token_hash, expires_on) 
+            VALUES ($1, $2, $3);
+            ",
+        )
+        .bind(account_id)
+        .bind(token_hash)
+        .bind(expires_on)
+        .execute(&self.connection)
+        .await;
+
+        match query_result {
+            Ok(_) => Ok(true),
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", err);
+
+                Err(Error::DatabaseQueryError(err))
+            }
+        }
+    }
+
+    /// Uses a password reset token to change the password of its account, revoking all the
+    /// sessions of the account. Returns the id of the account.
+    ///
+    /// Unknown, expired and already used tokens fail with `Error::InvalidResetToken`. A password
+    /// that does not follow the password policy for the email of the account fails with
+    /// `Error::WeakPassword` and the token can still be used.
+    pub async fn reset_password(&self, token_hash: String, password: String) -> Result<i32, Error> {
+        let mut tx = self.connection.begin().await.map_err(|err| {
+            tracing::event!(tracing::Level::ERROR, "{:?}", err);
+
+            Error::DatabaseQueryError(err)
+        })?;
+        let token_result = sqlx::query(
+            "
+            UPDATE password_reset_tokens 
+            SET used_on = NOW() 
+            FROM accounts 
+            WHERE password_reset_tokens.account_id = accounts.id 
+            AND token_hash = $1 AND used_on IS NULL AND expires_on > NOW() 
+            RETURNING accounts.id, accounts.email;
+            ",
+        )
+        .bind(token_hash)
+        .map(|row: PgRow| (row.get("id"), row.get("email")))
+        .fetch_optional(&mut tx)
+        .await;
+        let (account_id, email): (i32, String) = match token_result {
+            Ok(Some(account)) => account,
+            Ok(None) => return Err(Error::InvalidResetToken),
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "Use Reset Token Error: {:?}", err);
+
+                return Err(Error::DatabaseQueryError(err));
+            }
+        };
+
+        // Dropping the transaction rolls back the use of the token
+        validate_password(&password, Some(&email))?;
+
+        if let Err(err) = set_password(&mut tx, account_id, password, None).await {
+            tracing::event!(tracing::Level::ERROR, "Reset Password Error: {:?}", err);
+
+            return Err(Error::DatabaseQueryError(err));
+        }
+
+        match tx.commit().await {
+            Ok(_) => Ok(account_id),
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", err);
+
+                Err(Error::DatabaseQueryError(err))
+            }
+        }
+    }
+
+    pub async fn is_question_owner(
+        &self,
+        question_id: i32,
+        account_id: i32,
+    ) -> Result<bool, Error> {
+        let query_result =
+            sqlx::query("SELECT * FROM questions WHERE id = $1 AND account_id = $2;")
+                .bind(question_id)
+                .bind(account_id)
+                .fetch_optional(&self.connection)
+                .await;
+
+        match query_result {
+            Ok(question) => Ok(question.is_some()),
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", err);
+
+                Err(Error::DatabaseQueryError(err))
+            }
+        }
+    }
+
+    pub async fn is_answer_owner(&self, answer_id: i32, account_id: i32) -> Result<bool, Error> {
+        let query_result = sqlx::query("SELECT * FROM answers WHERE id = $1 AND account_id = $2;")
+            .bind(answer_id)
+            .bind(account_id)
+            .fetch_optional(&self.connection)
+            .await;
+
+        match query_result {
+            Ok(answer) => Ok(answer.is_some()),
+            Err(err) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", err);
+
+                Err(Error::DatabaseQueryError(err))
+            }
+        }
+    }
+}
+
+/// Hashes and stores the new password of an account and revokes all its sessions but `keep_session`.
+/// The unused password reset tokens of the account cannot be used anymore.
+///
+/// The failed logins of the account are forgotten, so a password reset unlocks the account.
+async fn set_password(
+    tx: &mut Transaction<'_, Postgres>,
+    account_id: i32,
+    password: String,
+    keep_session: Option<Uuid>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "
+        UPDATE accounts 
+        SET password = $1, failed_login_attempts = 0, login_locked_until = NULL 
+        WHERE id = $2;
+        ",
+    )
+    .bind(hash_password(password.as_bytes()))
+    .bind(account_id)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query(
+        "
+        UPDATE sessions 
+        SET revoked_on = NOW() 
+        WHERE account_id = $1 AND revoked_on IS NULL AND ($2::uuid IS NULL OR id <> $2);
+        ",
+    )
+    .bind(account_id)
+    .bind(keep_session)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query(
+        "
+        UPDATE password_reset_tokens 
+        SET used_on = NOW() 
+        WHERE account_id = $1 AND used_on IS NULL;
+        ",
+    )
+    .bind(account_id)
+    .execute(&mut *tx)
+    .await?;
+
+    Ok(())
+}
+
+/// Adds the tags which are not in the catalog yet. Tags are expected to be normalized.
+///
+/// It runs in the transaction storing the question, so the catalog never misses the tags of a
+/// question.
+async fn add_tags(tx: &mut Transaction<'_, Postgres>, tags: &[String]) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "
+        INSERT INTO tags (name) 
+        SELECT UNNEST($1::TEXT[]) 
+        ON CONFLICT (name) DO NOTHING;
+        ",
+    )
+    .bind(tags)
+    .execute(&mut *tx)
+    .await?;
+
+    Ok(())
+}
+
+/// Returns true when a query failed because a referenced row does not exist.
+fn is_foreign_key_violation(err: &sqlx::Error) -> bool {
+    err.as_database_error()
+        .and_then(|err| err.code())
+        .is_some_and(|code| code == FOREIGN_KEY_VIOLATION)
+}
+
+/// Returns the condition and the `ORDER BY` clause of the questions query for a sort order.
+///
+/// Every order falls back to the question id so pages are stable between requests.
+fn question_sort_clauses(sort: QuestionSort) -> (Option<&'static str>, &'static str) {
+    match sort {
+        QuestionSort::Newest => (None, "created_on DESC, id DESC"),
+        QuestionSort::Oldest => (None, "created_on ASC, id ASC"),
+        QuestionSort::Score => (None, "score DESC, created_on DESC, id DESC"),
+        QuestionSort::MostAnswers => (
+            None,
+            "(SELECT COUNT(*) FROM answers WHERE answers.corresponding_question = questions.id) DESC, created_on DESC, id DESC",
+        ),
+        QuestionSort::Unanswered => (
+            Some("NOT EXISTS (SELECT 1 FROM answers WHERE answers.corresponding_question = questions.id)"),
+            "created_on DESC, id DESC",
+        ),
+        QuestionSort::RecentlyActive => (
+            None,
+            "GREATEST(created_on, (SELECT MAX(answers.created_on) FROM answers WHERE answers.corresponding_question = questions.id)) DESC, id DESC",
+        ),
+    }
+}
+
+/// Builds the `WHERE` clause of the questions query. Questions held for review are never listed.
+///
+/// Parameters are numbered from `first_position`: first the tags of the filter, which are compared
+/// with the array operators so the GIN index on `questions.tags` can be used, and then the creation
+/// date and id of the cursor.
+fn questions_where_clause(
+    sort: QuestionSort,
+    tag_filter: Option<&TagFilter>,
+    after: Option<&Cursor>,
+    first_position: usize,
+) -> String {
+    let mut conditions = vec![String::from("moderation_status <> 'held'")];
+    let mut position = first_position;
+
+    if let Some(condition) = question_sort_clauses(sort).0 {
+        conditions.push(condition.to_string());
+    }
+
+    if let Some(tag_filter) = tag_filter {
+        let operator = match tag_filter.match_mode {
+            TagMatch::All => "@>",
+            TagMatch::Any => "&&",
+        };
+
+        conditions.push(format!("tags {} ${}", operator, position));
+        position += 1;
+    }
+
+    if let Some(Cursor::After { .. }) = after {
+        let operator = match sort {
+            QuestionSort::Oldest => ">",
+            _ => "<",
+        };
+
+        conditions.push(format!(
+            "(created_on, id) {} (${}, ${})",
+            operator,
+            position,
+            position + 1
+        ));
+    }
+
+    format!("WHERE {}", conditions.join(" AND "))
+}
+
+/// Escapes the wildcards of a `LIKE` pattern, so user input is matched literally.
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
 }
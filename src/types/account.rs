@@ -1,22 +1,182 @@
 use chrono::prelude::*;
+use handle_errors::Error;
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use uuid::Uuid;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// An account as it is stored in the database.
+///
+/// It does not implement `Serialize` because of its password hash, handlers reply with
+/// `PublicAccount` or `AccountDetails` instead.
+#[derive(Debug, Clone)]
 pub struct Account {
     pub id: AccountId,
     pub email: String,
-    pub password: String,
+    pub password: PasswordHash,
+    pub role: Role,
+    pub suspended_until: Option<DateTime<Utc>>,
+    pub banned_on: Option<DateTime<Utc>>,
+    /// Logins are rejected until this date after too many failed logins
+    pub login_locked_until: Option<DateTime<Utc>>,
+}
+
+impl Account {
+    pub fn is_login_locked(&self, now: DateTime<Utc>) -> bool {
+        self.login_locked_until.is_some_and(|until| until > now)
+    }
+}
+
+/// Argon2 hash of the password of an account. It cannot be serialized and it is redacted from the
+/// `Debug` output, so it never ends up in a response or in the logs.
+#[derive(Clone)]
+pub struct PasswordHash(String);
+
+impl PasswordHash {
+    pub fn new(hash: String) -> Self {
+        PasswordHash(hash)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for PasswordHash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PasswordHash(..)")
+    }
+}
+
+/// The public profile of an account.
+#[derive(Debug, Serialize, Clone)]
+pub struct PublicAccount {
+    pub id: AccountId,
+    pub email: String,
+    pub role: Role,
+}
+
+impl From<Account> for PublicAccount {
+    fn from(account: Account) -> Self {
+        PublicAccount {
+            id: account.id,
+            email: account.email,
+            role: account.role,
+        }
+    }
+}
+
+/// An account as admins see it, without the password.
+#[derive(Debug, Serialize, Clone)]
+pub struct AccountDetails {
+    pub id: AccountId,
+    pub email: String,
+    pub role: Role,
+    pub suspended_until: Option<DateTime<Utc>>,
+    pub banned_on: Option<DateTime<Utc>>,
+    /// Unverified accounts cannot post questions or answers
+    pub email_verified_on: Option<DateTime<Utc>>,
+}
+
+/// Returns `Error::AccountBanned` or `Error::AccountSuspended` when an account cannot be used.
+///
+/// A suspension is over once `suspended_until` has passed, a ban never ends.
+pub fn ensure_active(
+    suspended_until: Option<DateTime<Utc>>,
+    banned_on: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> Result<(), Error> {
+    if banned_on.is_some() {
+        return Err(Error::AccountBanned);
+    }
+
+    match suspended_until {
+        Some(until) if until > now => Err(Error::AccountSuspended),
+        _ => Ok(()),
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Hash, Clone, Default)]
 pub struct AccountId(pub i32);
 
+/// Role of an account. Roles are ordered, so every role can do what the previous ones can.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    #[default]
+    User,
+    /// Can review the flagged posts
+    Moderator,
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::User => "user",
+            Role::Moderator => "moderator",
+            Role::Admin => "admin",
+        }
+    }
+
+    /// Parses a role stored in the database, unknown roles have the permissions of a user.
+    pub fn from_db(value: &str) -> Self {
+        match value {
+            "moderator" => Role::Moderator,
+            "admin" => Role::Admin,
+            _ => Role::User,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct NewAccount {
     pub email: String,
     pub password: String,
 }
 
+pub const MIN_PASSWORD_LENGTH: usize = 10;
+pub const MAX_PASSWORD_LENGTH: usize = 128;
+
+/// Returns `Error::WeakPassword` when a password does not follow the password policy: it needs
+/// between 10 and 128 characters, at least one letter and at least one digit or symbol, and it
+/// cannot contain the email of the account.
+pub fn validate_password(password: &str, email: Option<&str>) -> Result<(), Error> {
+    let length = password.chars().count();
+    let has_letter = password.chars().any(char::is_alphabetic);
+    let has_other = password.chars().any(|c| !c.is_alphabetic());
+    let contains_email = email
+        .and_then(|email| email.split('@').next())
+        .filter(|user| user.len() >= 3)
+        .is_some_and(|user| password.to_lowercase().contains(&user.to_lowercase()));
+
+    if !(MIN_PASSWORD_LENGTH..=MAX_PASSWORD_LENGTH).contains(&length)
+        || !has_letter
+        || !has_other
+        || contains_email
+    {
+        return Err(Error::WeakPassword);
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ChangePassword {
+    pub current_password: String,
+    pub new_password: String,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct PasswordResetRequest {
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct PasswordResetConfirm {
+    pub token: String,
+    pub password: String,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Credentials {
     pub email: String,
@@ -29,4 +189,316 @@ pub struct Session {
     pub account_id: AccountId,
     // nbf means "not used before timestamp"
     pub nbf: DateTime<Utc>,
+    /// Tokens created before roles existed do not have this claim
+    #[serde(default)]
+    pub role: Role,
+    /// Login session the token belongs to, logging out revokes every token of the session
+    pub session_id: Uuid,
+}
+
+impl Session {
+    /// Returns `Error::ExpiredToken` or `Error::TokenNotYetValid` when the token of the session
+    /// cannot be used at `now`.
+    pub fn validate_time(&self, now: DateTime<Utc>) -> Result<(), Error> {
+        validate_token_time(self.nbf, self.exp, now)
+    }
+
+    /// Moderators and admins can edit and delete the posts of every account.
+    pub fn can_moderate(&self) -> bool {
+        self.role >= Role::Moderator
+    }
+}
+
+/// Body of the request changing the role of an account.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UpdateRole {
+    pub role: Role,
+}
+
+/// Body of the request suspending an account.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Suspension {
+    pub until: DateTime<Utc>,
+}
+
+/// Tokens returned by the login and refresh endpoints.
+///
+/// The access token is sent in the `Authorization` header and it expires after `expires_in`
+/// seconds. The refresh token can only be used once, to get a new pair of tokens.
+#[derive(Debug, Serialize, Clone)]
+pub struct TokenPair {
+    pub access_token: String,
+    pub refresh_token: String,
+    pub token_type: &'static str,
+    pub expires_in: i64,
+}
+
+/// Purpose claim of the email verification tokens, so access tokens cannot be used to verify an
+/// email.
+pub const EMAIL_VERIFICATION_PURPOSE: &str = "email_verification";
+
+/// Claims of the signed token of the email verification links.
+#[derive(Debug, Deserialize, Clone)]
+pub struct EmailVerification {
+    pub exp: DateTime<Utc>,
+    pub nbf: DateTime<Utc>,
+    pub account_id: AccountId,
+    /// The link only verifies the email it was sent to
+    pub email: String,
+    pub purpose: String,
+}
+
+impl EmailVerification {
+    /// Returns `Error::InvalidVerificationToken` when the token is not an email verification token
+    /// or it cannot be used at `now`.
+    pub fn validate(&self, now: DateTime<Utc>) -> Result<(), Error> {
+        if self.purpose != EMAIL_VERIFICATION_PURPOSE {
+            return Err(Error::InvalidVerificationToken);
+        }
+
+        validate_token_time(self.nbf, self.exp, now).map_err(|_| Error::InvalidVerificationToken)
+    }
+}
+
+/// Returns `Error::ExpiredToken` or `Error::TokenNotYetValid` when a token with the `nbf` and `exp`
+/// claims cannot be used at `now`.
+fn validate_token_time(
+    nbf: DateTime<Utc>,
+    exp: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> Result<(), Error> {
+    if now >= exp {
+        return Err(Error::ExpiredToken);
+    }
+
+    if now < nbf {
+        return Err(Error::TokenNotYetValid);
+    }
+
+    Ok(())
+}
+
+/// Response of the registration: the new account and the tokens of its first session.
+#[derive(Debug, Serialize, Clone)]
+pub struct Registration {
+    pub account: PublicAccount,
+    pub tokens: TokenPair,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+/// Returns the token of an `Authorization` header with the `Bearer` scheme of RFC 6750.
+///
+/// The scheme is case insensitive, so `bearer <token>` is accepted too.
+pub fn parse_bearer_token(authorization: &str) -> Result<&str, Error> {
+    let (scheme, token) = authorization
+        .trim()
+        .split_once(' ')
+        .ok_or(Error::MalformedToken)?;
+    let token = token.trim();
+
+    if !scheme.eq_ignore_ascii_case("bearer") || token.is_empty() || token.contains(' ') {
+        return Err(Error::MalformedToken);
+    }
+
+    Ok(token)
+}
+
+#[cfg(test)]
+mod account_tests {
+    use super::{
+        ensure_active, parse_bearer_token, validate_password, Account, AccountId,
+        EmailVerification, Error, PasswordHash, PublicAccount, Role, Session,
+        EMAIL_VERIFICATION_PURPOSE,
+    };
+    use chrono::{DateTime, Duration, Utc};
+    use uuid::Uuid;
+
+    fn session(nbf: DateTime<Utc>, exp: DateTime<Utc>) -> Session {
+        Session {
+            exp,
+            account_id: AccountId(1),
+            nbf,
+            role: Role::User,
+            session_id: Uuid::new_v4(),
+        }
+    }
+
+    #[test]
+    fn valid_password() {
+        assert!(validate_password("correct horse battery", Some("frank@email.com")).is_ok());
+        assert!(validate_password("s3cretpassw", None).is_ok());
+    }
+
+    #[test]
+    fn reject_weak_passwords() {
+        let long_password = format!("a1{}", "b".repeat(127));
+
+        for password in [
+            "short1",
+            "onlyletterss",
+            "1234567890",
+            long_password.as_str(),
+            "Frank-password-1",
+        ] {
+            assert_eq!(
+                format!(
+                    "{}",
+                    validate_password(password, Some("frank@email.com")).unwrap_err()
+                ),
+                format!("{}", Error::WeakPassword)
+            );
+        }
+    }
+
+    #[test]
+    fn public_account_without_password() {
+        let account = Account {
+            id: AccountId(1),
+            email: String::from("test@email.com"),
+            password: PasswordHash::new(String::from("$argon2i$v=19$m=4096,t=3,p=1$c2FsdA$aGFzaA")),
+            role: Role::User,
+            suspended_until: None,
+            banned_on: None,
+            login_locked_until: None,
+        };
+        let json = serde_json::to_value(PublicAccount::from(account.clone())).unwrap();
+
+        assert_eq!(
+            json,
+            serde_json::json!({ "id": 1, "email": "test@email.com", "role": "user" })
+        );
+        assert!(!format!("{:?}", account).contains("argon2"));
+    }
+
+    #[test]
+    fn parse_bearer_authorization() {
+        assert_eq!(
+            parse_bearer_token("Bearer v2.local.abc").unwrap(),
+            "v2.local.abc"
+        );
+        assert_eq!(
+            parse_bearer_token("bearer  v2.local.abc ").unwrap(),
+            "v2.local.abc"
+        );
+    }
+
+    #[test]
+    fn reject_malformed_authorization() {
+        for authorization in [
+            "v2.local.abc",
+            "Basic dXNlcjpwYXNz",
+            "Bearer ",
+            "Bearer a b",
+        ] {
+            assert_eq!(
+                format!("{}", parse_bearer_token(authorization).unwrap_err()),
+                format!("{}", Error::MalformedToken)
+            );
+        }
+    }
+
+    fn email_verification(purpose: &str, exp: DateTime<Utc>) -> EmailVerification {
+        EmailVerification {
+            exp,
+            nbf: exp - Duration::hours(24),
+            account_id: AccountId(1),
+            email: String::from("test@email.com"),
+            purpose: purpose.to_string(),
+        }
+    }
+
+    #[test]
+    fn valid_email_verification() {
+        let now = Utc::now();
+
+        assert!(
+            email_verification(EMAIL_VERIFICATION_PURPOSE, now + Duration::hours(1))
+                .validate(now)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn reject_invalid_email_verification() {
+        let now = Utc::now();
+
+        for verification in [
+            email_verification(EMAIL_VERIFICATION_PURPOSE, now - Duration::hours(1)),
+            email_verification("password_reset", now + Duration::hours(1)),
+        ] {
+            assert_eq!(
+                format!("{}", verification.validate(now).unwrap_err()),
+                format!("{}", Error::InvalidVerificationToken)
+            );
+        }
+    }
+
+    #[test]
+    fn valid_session_time() {
+        let now = Utc::now();
+
+        assert!(
+            session(now - Duration::minutes(1), now + Duration::minutes(1))
+                .validate_time(now)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn expired_session() {
+        let now = Utc::now();
+        let result =
+            session(now - Duration::minutes(2), now - Duration::minutes(1)).validate_time(now);
+
+        assert_eq!(
+            format!("{}", result.unwrap_err()),
+            format!("{}", Error::ExpiredToken)
+        );
+    }
+
+    #[test]
+    fn session_not_yet_valid() {
+        let now = Utc::now();
+        let result =
+            session(now + Duration::minutes(1), now + Duration::minutes(2)).validate_time(now);
+
+        assert_eq!(
+            format!("{}", result.unwrap_err()),
+            format!("{}", Error::TokenNotYetValid)
+        );
+    }
+
+    #[test]
+    fn active_account() {
+        let now = Utc::now();
+
+        assert!(ensure_active(None, None, now).is_ok());
+        assert!(ensure_active(Some(now - Duration::days(1)), None, now).is_ok());
+    }
+
+    #[test]
+    fn suspended_account() {
+        let now = Utc::now();
+        let result = ensure_active(Some(now + Duration::days(1)), None, now);
+
+        assert_eq!(
+            format!("{}", result.unwrap_err()),
+            format!("{}", Error::AccountSuspended)
+        );
+    }
+
+    #[test]
+    fn banned_account() {
+        let now = Utc::now();
+        let result = ensure_active(Some(now - Duration::days(1)), Some(now), now);
+
+        assert_eq!(
+            format!("{}", result.unwrap_err()),
+            format!("{}", Error::AccountBanned)
+        );
+    }
 }
@@ -1,13 +1,18 @@
+use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter};
 
-use crate::types::{account::AccountId, questions::QuestionId};
+use crate::types::{account::AccountId, moderation::ModerationStatus, questions::QuestionId};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Answer {
     pub id: AnswerId,
     pub content: String,
     pub question_id: QuestionId,
+    pub account_id: AccountId,
+    pub created_on: NaiveDateTime,
+    /// Sum of the up (+1) and down (-1) votes of the answer
+    pub score: i32,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -16,6 +21,13 @@ pub struct NewAnswer {
     pub question_id: QuestionId,
     #[serde(skip_deserializing)]
     pub account_id: AccountId,
+    #[serde(skip_deserializing)]
+    pub moderation_status: ModerationStatus,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UpdateAnswer {
+    pub content: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Hash, Clone)]
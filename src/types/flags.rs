@@ -0,0 +1,205 @@
+use chrono::NaiveDateTime;
+use handle_errors::Error;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::str::FromStr;
+use tracing::{event, Level};
+
+use crate::types::{account::AccountId, answers::AnswerId, questions::QuestionId};
+
+/// Reason a post is reported to the moderators.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FlagReason {
+    Spam,
+    Offensive,
+    Duplicate,
+    OffTopic,
+}
+
+impl FlagReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FlagReason::Spam => "spam",
+            FlagReason::Offensive => "offensive",
+            FlagReason::Duplicate => "duplicate",
+            FlagReason::OffTopic => "off_topic",
+        }
+    }
+
+    /// Parses a reason stored in the database. Unknown reasons are logged and reported as
+    /// offensive, so the flag still reaches the moderators.
+    pub fn from_db(value: &str) -> Self {
+        match value {
+            "spam" => FlagReason::Spam,
+            "offensive" => FlagReason::Offensive,
+            "duplicate" => FlagReason::Duplicate,
+            "off_topic" => FlagReason::OffTopic,
+            _ => {
+                event!(Level::WARN, "Unknown flag reason {:?}", value);
+
+                FlagReason::Offensive
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FlagStatus {
+    /// Waiting for a moderator
+    #[default]
+    Open,
+    /// A moderator agreed with the flag and took care of the post
+    Resolved,
+    /// A moderator disagreed with the flag
+    Dismissed,
+}
+
+impl FlagStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FlagStatus::Open => "open",
+            FlagStatus::Resolved => "resolved",
+            FlagStatus::Dismissed => "dismissed",
+        }
+    }
+}
+
+impl FromStr for FlagStatus {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "open" => Ok(FlagStatus::Open),
+            "resolved" => Ok(FlagStatus::Resolved),
+            "dismissed" => Ok(FlagStatus::Dismissed),
+            _ => Err(Error::InvalidFlagStatus(value.to_string())),
+        }
+    }
+}
+
+/// A report of a question or an answer.
+///
+/// Flags without `account_id` are created by the moderation when a post has more bad words than
+/// the moderation threshold.
+#[derive(Debug, Serialize, Clone)]
+pub struct Flag {
+    pub id: FlagId,
+    pub reason: FlagReason,
+    pub details: Option<String>,
+    pub status: FlagStatus,
+    pub account_id: Option<AccountId>,
+    pub question_id: Option<QuestionId>,
+    pub answer_id: Option<AnswerId>,
+    /// Content of the flagged post, so moderators can review it from the queue
+    pub content: String,
+    pub created_on: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct FlagId(pub i32);
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NewFlag {
+    pub reason: FlagReason,
+    pub details: Option<String>,
+}
+
+/// The post a flag is raised on.
+#[derive(Debug, Clone)]
+pub enum FlagTarget {
+    Question(QuestionId),
+    Answer(AnswerId),
+}
+
+impl FlagTarget {
+    /// Error returned when the flagged post does not exist.
+    pub fn not_found(&self) -> Error {
+        match self {
+            FlagTarget::Question(_) => Error::QuestionNotFound,
+            FlagTarget::Answer(_) => Error::AnswerNotFound,
+        }
+    }
+}
+
+/// What a moderator does with a flag.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FlagAction {
+    /// Closes the flag as resolved
+    Resolve,
+    /// Closes the flag as dismissed
+    Dismiss,
+    /// Deletes the flagged post together with its flags
+    DeleteTarget,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReviewFlag {
+    pub action: FlagAction,
+}
+
+/// Extract the `status` query parameter from "/moderation/flags" endpoint
+///
+/// When the parameter is missing, only the open flags are returned.
+///
+/// # Example query
+/// `/moderation/flags?status=dismissed&limit=10&offset=0`
+pub fn extract_flag_status(params: &HashMap<String, String>) -> Result<FlagStatus, Error> {
+    match params.get("status") {
+        Some(status) => status.parse::<FlagStatus>(),
+        None => Ok(FlagStatus::default()),
+    }
+}
+
+#[cfg(test)]
+mod flags_tests {
+    use super::{extract_flag_status, Error, FlagReason, FlagStatus, FlagTarget, HashMap};
+    use crate::types::{answers::AnswerId, questions::QuestionId};
+
+    #[test]
+    fn default_flag_status() {
+        let params = HashMap::new();
+
+        assert_eq!(extract_flag_status(&params).unwrap(), FlagStatus::Open);
+    }
+
+    #[test]
+    fn unknown_flag_status() {
+        let mut params = HashMap::new();
+
+        params.insert("status".to_string(), String::from("closed"));
+
+        let result = extract_flag_status(&params);
+
+        assert_eq!(
+            format!("{}", result.unwrap_err()),
+            format!("{}", Error::InvalidFlagStatus(String::from("closed")))
+        );
+    }
+
+    #[test]
+    fn missing_flag_target() {
+        assert_eq!(
+            format!("{}", FlagTarget::Question(QuestionId(1)).not_found()),
+            format!("{}", Error::QuestionNotFound)
+        );
+        assert_eq!(
+            format!("{}", FlagTarget::Answer(AnswerId(1)).not_found()),
+            format!("{}", Error::AnswerNotFound)
+        );
+    }
+
+    #[test]
+    fn stored_flag_reasons() {
+        for reason in [
+            FlagReason::Spam,
+            FlagReason::Offensive,
+            FlagReason::Duplicate,
+            FlagReason::OffTopic,
+        ] {
+            assert_eq!(FlagReason::from_db(reason.as_str()), reason);
+        }
+    }
+}
@@ -1,4 +1,9 @@
 pub mod account;
 pub mod answers;
+pub mod flags;
+pub mod moderation;
 pub mod pagination;
 pub mod questions;
+pub mod search;
+pub mod tags;
+pub mod votes;
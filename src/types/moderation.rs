@@ -0,0 +1,31 @@
+use serde::{Deserialize, Serialize};
+
+/// Moderation state of a question or an answer.
+///
+/// The order of the variants goes from the least to the most restrictive one, so the status of a
+/// question is the maximum of the statuses of its title and its content.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum ModerationStatus {
+    /// The content went through the profanity filter
+    #[default]
+    Approved,
+    /// The profanity service was unavailable, the content is published and it will be checked later
+    PendingRecheck,
+    /// The content has more bad words than the moderation threshold, it is published uncensored
+    /// until a moderator reviews it
+    Flagged,
+    /// The profanity service was unavailable, the content is hidden until it is checked
+    Held,
+}
+
+impl ModerationStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ModerationStatus::Approved => "approved",
+            ModerationStatus::PendingRecheck => "pending_recheck",
+            ModerationStatus::Flagged => "flagged",
+            ModerationStatus::Held => "held",
+        }
+    }
+}
@@ -1,7 +1,16 @@
-use std::collections::HashMap;
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use warp::{
+    http::header::{HeaderValue, LINK},
+    reply, Reply,
+};
 
 use handle_errors::Error;
 
+/// Maximum number of items of a page, larger limits are lowered to it
+pub const MAX_PAGE_LIMIT: u32 = 100;
+
 /// Pagination struct which is getting extract from query params,
 #[derive(Default, Debug, PartialEq)]
 pub struct Pagination {
@@ -9,6 +18,199 @@ pub struct Pagination {
     pub limit: Option<u32>,
     /// The index of the first item which has to be returned
     pub offset: u32,
+    /// The position after which items are returned when using cursor (keyset) pagination
+    pub after: Option<Cursor>,
+}
+
+/// Position of a page when using cursor (keyset) pagination.
+///
+/// Items are ordered by their creation date and id, so inserting new items between two requests
+/// does not skip or duplicate items like offset pagination does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Cursor {
+    /// The first page, requested with an empty cursor (`?after=&limit=10`)
+    Start,
+    /// The page after the item with this creation date and id
+    After { created_on: NaiveDateTime, id: i32 },
+}
+
+impl Cursor {
+    /// Encodes the position as an opaque string that clients send back in the `after` parameter.
+    pub fn encode(&self) -> String {
+        match self {
+            Cursor::Start => String::new(),
+            Cursor::After { created_on, id } => base64::encode_config(
+                format!("{}:{}", created_on.timestamp_micros(), id),
+                base64::URL_SAFE_NO_PAD,
+            ),
+        }
+    }
+
+    pub fn decode(cursor: &str) -> Result<Cursor, Error> {
+        if cursor.is_empty() {
+            return Ok(Cursor::Start);
+        }
+
+        let decoded = base64::decode_config(cursor, base64::URL_SAFE_NO_PAD)
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .ok_or(Error::InvalidCursor)?;
+        let (micros, id) = decoded.split_once(':').ok_or(Error::InvalidCursor)?;
+        let micros = micros.parse::<i64>().map_err(|_| Error::InvalidCursor)?;
+        let id = id.parse::<i32>().map_err(|_| Error::InvalidCursor)?;
+        let created_on = NaiveDateTime::from_timestamp_opt(
+            micros.div_euclid(1_000_000),
+            (micros.rem_euclid(1_000_000) * 1_000) as u32,
+        )
+        .ok_or(Error::InvalidCursor)?;
+
+        Ok(Cursor::After { created_on, id })
+    }
+}
+
+/// Envelope returned by every list endpoint.
+///
+/// `limit` is `null` when the request is not paginated and `next_cursor` is only present when using
+/// cursor pagination. The `links` are also sent in a `Link` header ([RFC 8288](https://www.rfc-editor.org/rfc/rfc8288)).
+#[derive(Debug, Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: i64,
+    pub limit: Option<u32>,
+    pub offset: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+    pub links: PageLinks,
+}
+
+#[derive(Debug, Default, Serialize, PartialEq, Eq)]
+pub struct PageLinks {
+    pub next: Option<String>,
+    pub prev: Option<String>,
+}
+
+impl<T> Page<T> {
+    /// Builds the page and its links.
+    ///
+    /// Links point to `path` keeping the query parameters of the request (`params`) other than the
+    /// pagination ones. In cursor pagination, only a `next` link is available.
+    pub fn new(
+        items: Vec<T>,
+        total: i64,
+        pagination: &Pagination,
+        next_cursor: Option<String>,
+        path: &str,
+        params: &HashMap<String, String>,
+    ) -> Self {
+        let mut links = PageLinks::default();
+
+        if let Some(limit) = pagination.limit {
+            if pagination.after.is_some() {
+                links.next = next_cursor.as_ref().map(|cursor| {
+                    page_link(
+                        path,
+                        params,
+                        &[("limit", limit.to_string()), ("after", cursor.clone())],
+                    )
+                });
+            } else {
+                let offset = pagination.offset;
+
+                if i64::from(offset) + i64::from(limit) < total {
+                    links.next = Some(page_link(
+                        path,
+                        params,
+                        &[
+                            ("limit", limit.to_string()),
+                            ("offset", (offset + limit).to_string()),
+                        ],
+                    ));
+                }
+
+                if offset > 0 {
+                    links.prev = Some(page_link(
+                        path,
+                        params,
+                        &[
+                            ("limit", limit.to_string()),
+                            ("offset", offset.saturating_sub(limit).to_string()),
+                        ],
+                    ));
+                }
+            }
+        }
+
+        Page {
+            items,
+            total,
+            limit: pagination.limit,
+            offset: pagination.offset,
+            next_cursor,
+            links,
+        }
+    }
+}
+
+impl PageLinks {
+    /// Value of the `Link` header, `None` when there are no links.
+    pub fn header_value(&self) -> Option<String> {
+        let links: Vec<String> = [("next", &self.next), ("prev", &self.prev)]
+            .iter()
+            .filter_map(|(rel, link)| {
+                link.as_ref()
+                    .map(|link| format!("<{}>; rel=\"{}\"", link, rel))
+            })
+            .collect();
+
+        if links.is_empty() {
+            return None;
+        }
+
+        Some(links.join(", "))
+    }
+}
+
+impl<T: Serialize + Send> Reply for Page<T> {
+    fn into_response(self) -> reply::Response {
+        let link_header = self.links.header_value();
+        let mut response = reply::json(&self).into_response();
+
+        if let Some(link_header) = link_header.and_then(|link| HeaderValue::from_str(&link).ok()) {
+            response.headers_mut().insert(LINK, link_header);
+        }
+
+        response
+    }
+}
+
+/// Builds the link to another page, replacing the pagination parameters of the request.
+fn page_link(
+    path: &str,
+    params: &HashMap<String, String>,
+    pagination: &[(&str, String)],
+) -> String {
+    let mut query: BTreeMap<&str, &str> = params
+        .iter()
+        .filter(|(key, _)| !matches!(key.as_str(), "limit" | "offset" | "after"))
+        .map(|(key, value)| (key.as_str(), value.as_str()))
+        .collect();
+
+    for (key, value) in pagination {
+        query.insert(key, value);
+    }
+
+    format!(
+        "{}?{}",
+        path,
+        serde_urlencoded::to_string(query).unwrap_or_default()
+    )
+}
+
+/// Returns true when the query contains any of the pagination parameters
+pub fn has_pagination(params: &HashMap<String, String>) -> bool {
+    ["limit", "offset", "after"]
+        .iter()
+        .any(|param| params.contains_key(*param))
 }
 
 /// Extract query parameters from "/questions" endpoint
@@ -19,6 +221,13 @@ pub struct Pagination {
 ///
 /// `/questions?limit=0&offset=10`
 ///
+/// Cursor pagination is used when the `after` parameter is present, in that case `limit` is
+/// required and `offset` is ignored. An empty `after` returns the first page.
+///
+/// `/questions?limit=10&after=MTY2MDU3NjAwMDAwMDAwMDo0Mg`
+///
+/// `limit` is capped to `MAX_PAGE_LIMIT`.
+///
 /// # Example usage
 /// ```rust
 /// let mut query = HashMap::new();
@@ -32,19 +241,23 @@ pub struct Pagination {
 /// assert_eq!(p.offset, 10);
 /// ```
 pub fn extract_pagination(params: HashMap<String, String>) -> Result<Pagination, Error> {
+    if let Some(after) = params.get("after") {
+        let limit = parse_limit(params.get("limit").ok_or(Error::MissingParameters)?)?;
+
+        return Ok(Pagination {
+            limit: Some(limit),
+            offset: 0,
+            after: Some(Cursor::decode(after)?),
+        });
+    }
+
     // Could be improve in the future
     if !params.contains_key("limit") || !params.contains_key("offset") {
         return Err(Error::MissingParameters);
     }
 
     // Takes a "start" query param from the url and tries to convert it to a number
-    let limit = Some(
-        params
-            .get("limit")
-            .unwrap()
-            .parse::<u32>()
-            .map_err(Error::ParseError)?,
-    );
+    let limit = Some(parse_limit(params.get("limit").unwrap())?);
     // Takes a "end" query param from the url and tries to convert it to a number
     let offset = params
         .get("offset")
@@ -52,12 +265,45 @@ pub fn extract_pagination(params: HashMap<String, String>) -> Result<Pagination,
         .parse::<u32>()
         .map_err(Error::ParseError)?;
 
-    Ok(Pagination { limit, offset })
+    Ok(Pagination {
+        limit,
+        offset,
+        after: None,
+    })
+}
+
+fn parse_limit(limit: &str) -> Result<u32, Error> {
+    let limit = limit.parse::<u32>().map_err(Error::ParseError)?;
+
+    Ok(limit.min(MAX_PAGE_LIMIT))
 }
 
 #[cfg(test)]
 mod pagination_tests {
-    use super::{extract_pagination, Error, HashMap, Pagination};
+    use super::{
+        extract_pagination, Cursor, Error, HashMap, NaiveDateTime, Page, PageLinks, Pagination,
+        MAX_PAGE_LIMIT,
+    };
+
+    #[test]
+    fn cap_limit_parameter() {
+        let mut params = HashMap::new();
+
+        params.insert("limit".to_string(), u32::MAX.to_string());
+        params.insert("offset".to_string(), String::from("0"));
+
+        assert_eq!(
+            extract_pagination(params.clone()).unwrap().limit,
+            Some(MAX_PAGE_LIMIT)
+        );
+
+        params.insert("after".to_string(), String::new());
+
+        assert_eq!(
+            extract_pagination(params).unwrap().limit,
+            Some(MAX_PAGE_LIMIT)
+        );
+    }
 
     #[test]
     fn valid_pagination() {
@@ -72,7 +318,8 @@ mod pagination_tests {
             result.unwrap(),
             Pagination {
                 limit: Some(1),
-                offset: 1
+                offset: 1,
+                after: None
             }
         );
     }
@@ -136,4 +383,154 @@ mod pagination_tests {
             format!("{}", Error::ParseError(parse_int_error.unwrap_err()))
         );
     }
+
+    #[test]
+    fn valid_cursor_pagination() {
+        let cursor = Cursor::After {
+            created_on: NaiveDateTime::from_timestamp_opt(1_660_576_000, 123_456_000).unwrap(),
+            id: 42,
+        };
+        let mut params = HashMap::new();
+
+        params.insert("limit".to_string(), String::from("10"));
+        params.insert("after".to_string(), cursor.encode());
+
+        let result = extract_pagination(params);
+
+        assert_eq!(
+            result.unwrap(),
+            Pagination {
+                limit: Some(10),
+                offset: 0,
+                after: Some(cursor)
+            }
+        );
+    }
+
+    #[test]
+    fn empty_cursor_starts_cursor_pagination() {
+        let mut params = HashMap::new();
+
+        params.insert("limit".to_string(), String::from("10"));
+        params.insert("after".to_string(), String::new());
+
+        let result = extract_pagination(params);
+
+        assert_eq!(result.unwrap().after, Some(Cursor::Start));
+    }
+
+    #[test]
+    fn missing_limit_with_cursor() {
+        let mut params = HashMap::new();
+
+        params.insert("after".to_string(), String::new());
+
+        let result = extract_pagination(params);
+
+        assert_eq!(
+            format!("{}", result.unwrap_err()),
+            format!("{}", Error::MissingParameters)
+        );
+    }
+
+    #[test]
+    fn invalid_cursor() {
+        let mut params = HashMap::new();
+
+        params.insert("limit".to_string(), String::from("10"));
+        params.insert("after".to_string(), String::from("not-a-cursor"));
+
+        let result = extract_pagination(params);
+
+        assert_eq!(
+            format!("{}", result.unwrap_err()),
+            format!("{}", Error::InvalidCursor)
+        );
+    }
+
+    #[test]
+    fn page_links_in_the_middle() {
+        let mut params = HashMap::new();
+
+        params.insert("sort".to_string(), String::from("score"));
+        params.insert("limit".to_string(), String::from("10"));
+        params.insert("offset".to_string(), String::from("10"));
+
+        let pagination = extract_pagination(params.clone()).unwrap();
+        let page = Page::new(vec![1, 2], 35, &pagination, None, "/questions", &params);
+
+        assert_eq!(
+            page.links,
+            PageLinks {
+                next: Some(String::from("/questions?limit=10&offset=20&sort=score")),
+                prev: Some(String::from("/questions?limit=10&offset=0&sort=score")),
+            }
+        );
+        assert_eq!(
+            page.links.header_value().unwrap(),
+            String::from(
+                "</questions?limit=10&offset=20&sort=score>; rel=\"next\", </questions?limit=10&offset=0&sort=score>; rel=\"prev\""
+            )
+        );
+    }
+
+    #[test]
+    fn page_links_in_the_last_page() {
+        let mut params = HashMap::new();
+
+        params.insert("limit".to_string(), String::from("10"));
+        params.insert("offset".to_string(), String::from("30"));
+
+        let pagination = extract_pagination(params.clone()).unwrap();
+        let page = Page::new(vec![1, 2], 32, &pagination, None, "/tags", &params);
+
+        assert_eq!(page.links.next, None);
+        assert_eq!(
+            page.links.prev,
+            Some(String::from("/tags?limit=10&offset=20"))
+        );
+    }
+
+    #[test]
+    fn page_links_with_cursor() {
+        let mut params = HashMap::new();
+
+        params.insert("limit".to_string(), String::from("10"));
+        params.insert("after".to_string(), String::new());
+
+        let pagination = extract_pagination(params.clone()).unwrap();
+        let page = Page::new(
+            vec![1, 2],
+            32,
+            &pagination,
+            Some(String::from("MTY2MDU3NjAwMDAwMDAwMDo0Mg")),
+            "/questions",
+            &params,
+        );
+
+        assert_eq!(
+            page.links,
+            PageLinks {
+                next: Some(String::from(
+                    "/questions?after=MTY2MDU3NjAwMDAwMDAwMDo0Mg&limit=10"
+                )),
+                prev: None,
+            }
+        );
+    }
+
+    #[test]
+    fn page_without_pagination() {
+        let params = HashMap::new();
+        let page = Page::new(
+            vec![1, 2],
+            2,
+            &Pagination::default(),
+            None,
+            "/tags",
+            &params,
+        );
+
+        assert_eq!(page.links.header_value(), None);
+    }
 }
@@ -1,7 +1,11 @@
+use chrono::NaiveDateTime;
+use handle_errors::Error;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
+use std::str::FromStr;
 
-use crate::types::account::AccountId;
+use crate::types::{account::AccountId, answers::AnswerId, moderation::ModerationStatus};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Question {
@@ -9,6 +13,14 @@ pub struct Question {
     pub title: String,
     pub content: String,
     pub tags: Option<Vec<String>>,
+    /// The answer chosen by the author of the question, it can only be changed through the accept endpoints
+    #[serde(skip_deserializing)]
+    pub accepted_answer: Option<AnswerId>,
+    /// Sum of the up (+1) and down (-1) votes of the question
+    #[serde(skip_deserializing)]
+    pub score: i32,
+    #[serde(skip_deserializing)]
+    pub created_on: NaiveDateTime,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -18,6 +30,8 @@ pub struct NewQuestion {
     pub tags: Option<Vec<String>>,
     #[serde(skip_deserializing)]
     pub account_id: AccountId,
+    #[serde(skip_deserializing)]
+    pub moderation_status: ModerationStatus,
 }
 
 #[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Hash, Clone)]
@@ -28,3 +42,92 @@ impl Display for QuestionId {
         write!(f, "id: {}", self.0)
     }
 }
+
+/// The order in which the "/questions" endpoint returns the questions.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum QuestionSort {
+    /// Most recently created questions first
+    #[default]
+    Newest,
+    /// Oldest questions first
+    Oldest,
+    /// Highest voted questions first
+    Score,
+    /// Questions with more answers first
+    MostAnswers,
+    /// Only questions without answers, newest first
+    Unanswered,
+    /// Questions with the most recent activity (creation or new answer) first
+    RecentlyActive,
+}
+
+impl QuestionSort {
+    /// Cursor pagination is only available for the orders based on the creation date.
+    pub fn supports_cursor(&self) -> bool {
+        matches!(self, QuestionSort::Newest | QuestionSort::Oldest)
+    }
+}
+
+impl FromStr for QuestionSort {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "newest" => Ok(QuestionSort::Newest),
+            "oldest" => Ok(QuestionSort::Oldest),
+            "score" => Ok(QuestionSort::Score),
+            "most_answers" => Ok(QuestionSort::MostAnswers),
+            "unanswered" => Ok(QuestionSort::Unanswered),
+            "recently_active" => Ok(QuestionSort::RecentlyActive),
+            _ => Err(Error::InvalidSortParameter(value.to_string())),
+        }
+    }
+}
+
+/// Extract the `sort` query parameter from "/questions" endpoint
+///
+/// When the parameter is missing, questions are sorted by `QuestionSort::Newest`.
+///
+/// # Example query
+/// `/questions?sort=most_answers&limit=10&offset=0`
+pub fn extract_sort(params: &HashMap<String, String>) -> Result<QuestionSort, Error> {
+    match params.get("sort") {
+        Some(sort) => sort.parse::<QuestionSort>(),
+        None => Ok(QuestionSort::default()),
+    }
+}
+
+#[cfg(test)]
+mod questions_tests {
+    use super::{extract_sort, Error, HashMap, QuestionSort};
+
+    #[test]
+    fn default_sort() {
+        let params = HashMap::new();
+
+        assert_eq!(extract_sort(&params).unwrap(), QuestionSort::Newest);
+    }
+
+    #[test]
+    fn valid_sort() {
+        let mut params = HashMap::new();
+
+        params.insert("sort".to_string(), String::from("most_answers"));
+
+        assert_eq!(extract_sort(&params).unwrap(), QuestionSort::MostAnswers);
+    }
+
+    #[test]
+    fn unknown_sort() {
+        let mut params = HashMap::new();
+
+        params.insert("sort".to_string(), String::from("popular"));
+
+        let result = extract_sort(&params);
+
+        assert_eq!(
+            format!("{}", result.unwrap_err()),
+            format!("{}", Error::InvalidSortParameter(String::from("popular")))
+        );
+    }
+}
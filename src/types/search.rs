@@ -0,0 +1,113 @@
+use handle_errors::Error;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::types::{answers::AnswerId, questions::QuestionId};
+
+/// Maximum number of characters of a search query
+pub const MAX_SEARCH_QUERY_LENGTH: usize = 200;
+/// Delimiters of the matching words in the snippets returned by the database. They are control
+/// characters instead of HTML tags, so they can be told apart from the HTML written by the users.
+const SNIPPET_START: char = '\u{2}';
+const SNIPPET_STOP: char = '\u{3}';
+
+/// Options of `ts_headline` producing the snippets read by `highlight_snippet`.
+pub fn snippet_options() -> String {
+    format!(
+        "StartSel=\"{}\", StopSel=\"{}\"",
+        SNIPPET_START, SNIPPET_STOP
+    )
+}
+
+/// Escapes the HTML of a snippet returned by the database and wraps its matching words in `<mark>`
+/// tags, so the only tags of the snippet are the ones added by the server.
+pub fn highlight_snippet(snippet: &str) -> String {
+    let mut highlighted = String::with_capacity(snippet.len());
+
+    for c in snippet.chars() {
+        match c {
+            SNIPPET_START => highlighted.push_str("<mark>"),
+            SNIPPET_STOP => highlighted.push_str("</mark>"),
+            '&' => highlighted.push_str("&amp;"),
+            '<' => highlighted.push_str("&lt;"),
+            '>' => highlighted.push_str("&gt;"),
+            '"' => highlighted.push_str("&quot;"),
+            '\'' => highlighted.push_str("&#39;"),
+            _ => highlighted.push(c),
+        }
+    }
+
+    highlighted
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchResultKind {
+    Question,
+    Answer,
+}
+
+/// A question or an answer matching a search query.
+///
+/// `snippet` is a fragment of the content, HTML escaped, with the matching words wrapped in `<mark>`
+/// tags.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SearchResult {
+    pub kind: SearchResultKind,
+    pub question_id: QuestionId,
+    pub answer_id: Option<AnswerId>,
+    pub title: String,
+    pub snippet: String,
+    pub rank: f32,
+}
+
+/// Extract the search query from the `q` query parameter of "/search" endpoint
+///
+/// # Example query
+/// `/search?q=async traits&limit=10&offset=0`
+pub fn extract_search_query(params: &HashMap<String, String>) -> Result<String, Error> {
+    let query = params.get("q").map(|q| q.trim()).unwrap_or_default();
+
+    if query.is_empty() || query.chars().count() > MAX_SEARCH_QUERY_LENGTH {
+        return Err(Error::InvalidSearchQuery);
+    }
+
+    Ok(query.to_string())
+}
+
+#[cfg(test)]
+mod search_tests {
+    use super::{extract_search_query, highlight_snippet, Error, HashMap};
+
+    #[test]
+    fn escape_snippet_html() {
+        assert_eq!(
+            highlight_snippet("<script>alert('x')</script> and \u{2}async\u{3} & \"traits\""),
+            "&lt;script&gt;alert(&#39;x&#39;)&lt;/script&gt; and <mark>async</mark> &amp; &quot;traits&quot;"
+        );
+    }
+
+    #[test]
+    fn valid_search_query() {
+        let mut params = HashMap::new();
+
+        params.insert("q".to_string(), String::from("  async traits "));
+
+        assert_eq!(
+            extract_search_query(&params).unwrap(),
+            String::from("async traits")
+        );
+    }
+
+    #[test]
+    fn missing_search_query() {
+        let mut params = HashMap::new();
+
+        params.insert("q".to_string(), String::from("   "));
+
+        assert_eq!(
+            format!("{}", extract_search_query(&params).unwrap_err()),
+            format!("{}", Error::InvalidSearchQuery)
+        );
+    }
+}
@@ -0,0 +1,205 @@
+use handle_errors::Error;
+use percent_encoding::percent_decode_str;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Maximum number of characters of a tag
+pub const MAX_TAG_LENGTH: usize = 35;
+
+/// Tag of the catalog together with the number of questions using it.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Tag {
+    pub name: String,
+    pub description: Option<String>,
+    pub question_count: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UpdateTag {
+    pub description: Option<String>,
+}
+
+/// How the tags of a `TagFilter` are matched against the tags of a question.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TagMatch {
+    /// The question has to contain every tag of the filter
+    #[default]
+    All,
+    /// The question has to contain at least one tag of the filter
+    Any,
+}
+
+/// Tag filter which is getting extract from query params.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagFilter {
+    pub tags: Vec<String>,
+    pub match_mode: TagMatch,
+}
+
+/// A tag can only contain ASCII letters, digits and the characters `-`, `+`, `#` and `.`
+/// (e.g. `c++`, `c#`, `asp.net`, `async-await`), with a maximum of `MAX_TAG_LENGTH` characters.
+pub fn is_valid_tag(tag: &str) -> bool {
+    !tag.is_empty()
+        && tag.len() <= MAX_TAG_LENGTH
+        && tag
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '+' | '#' | '.'))
+}
+
+/// Normalizes a tag so `Rust`, `rust` and ` rust` are stored as the same tag.
+///
+/// The tag is trimmed and lowercased, and it returns `Error::InvalidTag` when the result is not a
+/// valid tag (see `is_valid_tag`).
+pub fn normalize_tag(tag: &str) -> Result<String, Error> {
+    let normalized_tag = tag.trim().to_lowercase();
+
+    if !is_valid_tag(&normalized_tag) {
+        return Err(Error::InvalidTag(tag.to_string()));
+    }
+
+    Ok(normalized_tag)
+}
+
+/// Normalizes a tag sent as a path segment, e.g. `/tags/c%23`. Warp does not percent-decode the
+/// path, so the segment is decoded before it is normalized.
+pub fn normalize_tag_param(param: &str) -> Result<String, Error> {
+    let tag = percent_decode_str(param)
+        .decode_utf8()
+        .map_err(|_| Error::InvalidTag(param.to_string()))?;
+
+    normalize_tag(&tag)
+}
+
+/// Normalizes the tags of a question, removing the duplicates that appear after normalizing.
+pub fn normalize_tags(tags: Option<Vec<String>>) -> Result<Option<Vec<String>>, Error> {
+    let tags = match tags {
+        Some(tags) => tags,
+        None => return Ok(None),
+    };
+    let mut normalized_tags: Vec<String> = Vec::with_capacity(tags.len());
+
+    for tag in tags {
+        let normalized_tag = normalize_tag(&tag)?;
+
+        if !normalized_tags.contains(&normalized_tag) {
+            normalized_tags.push(normalized_tag);
+        }
+    }
+
+    Ok(Some(normalized_tags))
+}
+
+/// Extract the tag filter from "/questions" endpoint
+///
+/// Returns `None` when there is no `tags` query parameter. The `match` parameter is optional
+/// and defaults to `all`. Tags are normalized the same way they are when a question is stored.
+///
+/// # Example query
+/// `/questions?tags=rust,async&match=any`
+pub fn extract_tag_filter(params: &HashMap<String, String>) -> Result<Option<TagFilter>, Error> {
+    let tags_param = match params.get("tags") {
+        Some(tags) => tags,
+        None => return Ok(None),
+    };
+    let match_mode = match params.get("match").map(String::as_str) {
+        None | Some("all") => TagMatch::All,
+        Some("any") => TagMatch::Any,
+        Some(value) => return Err(Error::InvalidMatchParameter(value.to_string())),
+    };
+    let tags = normalize_tags(Some(tags_param.split(',').map(String::from).collect()))?
+        .unwrap_or_default();
+
+    Ok(Some(TagFilter { tags, match_mode }))
+}
+
+#[cfg(test)]
+mod tags_tests {
+    use super::{
+        extract_tag_filter, normalize_tag_param, normalize_tags, Error, HashMap, TagFilter,
+        TagMatch,
+    };
+
+    #[test]
+    fn missing_tags_parameter() {
+        let params = HashMap::new();
+
+        assert_eq!(extract_tag_filter(&params).unwrap(), None);
+    }
+
+    #[test]
+    fn valid_tag_filter() {
+        let mut params = HashMap::new();
+
+        params.insert("tags".to_string(), String::from("Rust, async"));
+        params.insert("match".to_string(), String::from("any"));
+
+        assert_eq!(
+            extract_tag_filter(&params).unwrap(),
+            Some(TagFilter {
+                tags: vec![String::from("rust"), String::from("async")],
+                match_mode: TagMatch::Any
+            })
+        );
+    }
+
+    #[test]
+    fn invalid_tag() {
+        let mut params = HashMap::new();
+
+        params.insert("tags".to_string(), String::from("rust,,async"));
+
+        assert_eq!(
+            format!("{}", extract_tag_filter(&params).unwrap_err()),
+            format!("{}", Error::InvalidTag(String::new()))
+        );
+    }
+
+    #[test]
+    fn normalize_duplicated_tags() {
+        let tags = vec![
+            String::from("Rust"),
+            String::from(" rust"),
+            String::from("rust "),
+            String::from("Async"),
+        ];
+
+        assert_eq!(
+            normalize_tags(Some(tags)).unwrap(),
+            Some(vec![String::from("rust"), String::from("async")])
+        );
+    }
+
+    #[test]
+    fn normalize_too_long_tag() {
+        let tag = "a".repeat(36);
+
+        assert_eq!(
+            format!("{}", normalize_tags(Some(vec![tag.clone()])).unwrap_err()),
+            format!("{}", Error::InvalidTag(tag))
+        );
+    }
+
+    #[test]
+    fn invalid_match_parameter() {
+        let mut params = HashMap::new();
+
+        params.insert("tags".to_string(), String::from("rust"));
+        params.insert("match".to_string(), String::from("some"));
+
+        assert_eq!(
+            format!("{}", extract_tag_filter(&params).unwrap_err()),
+            format!("{}", Error::InvalidMatchParameter(String::from("some")))
+        );
+    }
+
+    #[test]
+    fn percent_encoded_tag_param() {
+        assert_eq!(normalize_tag_param("c%23").unwrap(), "c#");
+        assert_eq!(normalize_tag_param("C%2B%2B").unwrap(), "c++");
+        assert_eq!(normalize_tag_param("rust").unwrap(), "rust");
+        assert_eq!(
+            format!("{}", normalize_tag_param("%FF").unwrap_err()),
+            format!("{}", Error::InvalidTag(String::from("%FF")))
+        );
+    }
+}
@@ -0,0 +1,61 @@
+use handle_errors::Error;
+use serde::{Deserialize, Serialize};
+
+use crate::types::{answers::AnswerId, questions::QuestionId};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum VoteDirection {
+    Up,
+    Down,
+}
+
+impl VoteDirection {
+    /// The amount the vote adds to the score of the post.
+    pub fn value(&self) -> i16 {
+        match self {
+            VoteDirection::Up => 1,
+            VoteDirection::Down => -1,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NewVote {
+    pub direction: VoteDirection,
+}
+
+/// The post a vote is casted on.
+#[derive(Debug, Clone)]
+pub enum VoteTarget {
+    Question(QuestionId),
+    Answer(AnswerId),
+}
+
+impl VoteTarget {
+    /// Error returned when the post of the vote does not exist.
+    pub fn not_found(&self) -> Error {
+        match self {
+            VoteTarget::Question(_) => Error::QuestionNotFound,
+            VoteTarget::Answer(_) => Error::AnswerNotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod votes_tests {
+    use super::{Error, VoteTarget};
+    use crate::types::{answers::AnswerId, questions::QuestionId};
+
+    #[test]
+    fn missing_vote_target() {
+        assert_eq!(
+            format!("{}", VoteTarget::Question(QuestionId(1)).not_found()),
+            format!("{}", Error::QuestionNotFound)
+        );
+        assert_eq!(
+            format!("{}", VoteTarget::Answer(AnswerId(1)).not_found()),
+            format!("{}", Error::AnswerNotFound)
+        );
+    }
+}